@@ -0,0 +1,126 @@
+// Compares the automaton with the original approach of trying every word at
+// every position, on a generated calibration document
+
+use crate::calibrate;
+use crate::vocabulary::Vocabulary;
+use anyhow::{bail, Result};
+use std::time::Instant;
+
+const WORDS: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+const FILLER: [&str; 6] = ["é", "д", "€", "🎄", "twx", "eigh"];
+
+// xorshift64, enough to make a reproducible document without a dependency
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+// Lines of letters with the odd digit, digit word and non-ASCII character,
+// about `size` bytes in all. Lines are around `line_len` bytes with one token
+// in `word_every` a digit or digit word.
+pub fn generate(size: usize, seed: u64, line_len: usize, word_every: usize) -> Vec<Vec<u8>> {
+    let mut rng = Rng(seed | 1);
+    let mut lines = Vec::new();
+    let mut total = 0;
+    while total < size {
+        let mut line = Vec::new();
+        let len = line_len / 2 + rng.below(line_len);
+        while line.len() < len {
+            if rng.below(word_every) == 0 {
+                match rng.below(3) {
+                    0 => line.push(b'0' + rng.below(10) as u8),
+                    _ => line.extend_from_slice(WORDS[rng.below(WORDS.len())].as_bytes()),
+                }
+            } else if rng.below(10) == 0 {
+                line.extend_from_slice(FILLER[rng.below(FILLER.len())].as_bytes());
+            } else {
+                line.push(b'a' + rng.below(26) as u8);
+            }
+        }
+        total += line.len() + 1;
+        lines.push(line);
+    }
+    lines
+}
+
+fn match_word(s: &str) -> Option<u32> {
+    let c = s.chars().next()?;
+    if c.is_ascii_digit() {
+        return c.to_digit(10);
+    }
+    (0..)
+        .zip(WORDS)
+        .find(|(_, word)| s.starts_with(word))
+        .map(|(i, _)| i)
+}
+
+// The original scan, made to step over char boundaries only
+fn naive_sum(lines: &[Vec<u8>]) -> u32 {
+    let mut sum = 0;
+    for line in lines {
+        let line = String::from_utf8_lossy(line);
+        let positions = line.char_indices().map(|(i, _)| i);
+        if let Some(first) = positions.clone().find_map(|i| match_word(&line[i..])) {
+            sum += first * 10;
+        }
+        if let Some(last) = positions.rev().find_map(|i| match_word(&line[i..])) {
+            sum += last;
+        }
+    }
+    sum
+}
+
+fn compare(name: &str, lines: Vec<Vec<u8>>) -> Result<()> {
+    let start = Instant::now();
+    let naive = naive_sum(&lines);
+    let naive_duration = start.elapsed();
+
+    let start = Instant::now();
+    let matcher = Vocabulary::english().compile();
+    let sum = calibrate(lines, &matcher).sum();
+    let duration = start.elapsed();
+
+    if sum != naive {
+        bail!("{name}: automaton and naive scan disagree");
+    }
+    let speedup = naive_duration.as_secs_f64() / duration.as_secs_f64();
+    println!("{name}: {sum}, naive time: {naive_duration:?}, automaton time: {duration:?}, speedup: {speedup:.1}x");
+    Ok(())
+}
+
+// Short lines dense with digits like the puzzle input, where the naive scan
+// stops early, and long lines with few digits, where it tries every word at
+// nearly every position
+pub fn run(megabytes: usize) -> Result<()> {
+    let size = megabytes << 20;
+    let start = Instant::now();
+    let puzzle_like = generate(size, 2023, 40, 8);
+    let sparse = generate(size, 2023, 4000, 500);
+    let duration = start.elapsed();
+    println!("generated 2 x {megabytes} MB, time: {duration:?}");
+
+    compare("puzzle-like", puzzle_like)?;
+    compare("sparse", sparse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agrees_with_naive() {
+        let lines = generate(1 << 16, 1, 40, 8);
+        assert!(lines.iter().any(|line| !line.is_ascii()));
+        let naive = naive_sum(&lines);
+        let calibration = calibrate(lines, &Vocabulary::english().compile());
+        assert_eq!(naive, calibration.sum());
+    }
+}
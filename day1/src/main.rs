@@ -1,78 +1,202 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::time::Instant;
 
-fn part1(filename: &str) -> Result<u32> {
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
-    let mut sum = 0;
-    for line in reader.lines() {
-        let line = line?;
-        let first = line
-            .chars()
-            .find(|c| c.is_ascii_digit())
-            .unwrap_or( '0')
-            .to_digit(10).unwrap();
-        let last = line
-            .chars()
-            .rfind(|c| c.is_ascii_digit())
-            .unwrap_or( '0')
-            .to_digit(10).unwrap();
-        sum += first * 10 + last;
-    }
-    Ok(sum)
+mod bench;
+mod vocabulary;
+
+use vocabulary::{Match, Matcher, Vocabulary};
+
+// Every line with the first and last digit the matcher found in it, if any
+struct Calibration {
+    lines: Vec<Vec<u8>>,
+    matches: Vec<Option<(Match, Match)>>,
 }
 
-fn match_number(s: &str) -> Option<u32> {
-    const NUM_STRINGS: [&str; 10] = [
-        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
-    ];
-
-    let c = s.chars().next()?;
-    if c.is_ascii_digit() {
-        return c.to_digit(10);
-    };
-    for (i, num) in NUM_STRINGS.iter().enumerate() {
-        if s.starts_with(num) {
-            return Some(i as u32);
+impl Calibration {
+    fn value(&self, i: usize) -> Option<u32> {
+        self.matches[i].map(|(first, last)| first.digit * 10 + last.digit)
+    }
+
+    fn sum(&self) -> u32 {
+        (0..self.lines.len()).filter_map(|i| self.value(i)).sum()
+    }
+
+    // Line numbers, counted from 1, of the lines without any digit. Blank
+    // lines don't count.
+    fn missing(&self) -> Vec<usize> {
+        (0..self.lines.len())
+            .filter(|&i| self.matches[i].is_none() && !is_blank(&self.lines[i]))
+            .map(|i| i + 1)
+            .collect()
+    }
+
+    // Each line with the words its digits came from and the decoded value
+    fn explain(&self) -> String {
+        let word = |line: &[u8], m: Match| {
+            String::from_utf8_lossy(&line[m.start..m.start + m.len]).into_owned()
+        };
+        let mut out = String::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if is_blank(line) {
+                continue;
+            }
+            let decoded = match self.matches[i] {
+                Some((first, last)) => format!(
+                    "{:>2} = {} + {}",
+                    first.digit * 10 + last.digit,
+                    word(line, first),
+                    word(line, last)
+                ),
+                None => "no digits".to_string(),
+            };
+            let line = String::from_utf8_lossy(line);
+            out += &format!("{:>6}  {line:<40}  {decoded}\n", i + 1);
         }
+        out
     }
-    None
 }
 
-fn part2(filename: &str) -> Result<u32> {
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+// Lines as raw bytes, so text that isn't valid UTF-8 is still scanned
+fn read_lines(filename: &str) -> Result<Vec<Vec<u8>>> {
     let file = File::open(filename)?;
     let reader = BufReader::new(file);
-    let mut sum = 0;
-    for line in reader.lines() {
-        let line = line?;
-        for i in 0..line.len() {
-            if let Some(first) = match_number(&line[i..]) {
-                sum += first * 10;
-                break;
-            }
+    let mut lines = Vec::new();
+    for line in reader.split(b'\n') {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
         }
-        for i in (0..line.len()).rev() {
-            if let Some(last) = match_number(&line[i..]) {
-                sum += last;
-                break;
-            }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn calibrate(lines: Vec<Vec<u8>>, matcher: &Matcher) -> Calibration {
+    let matches = lines.iter().map(|line| matcher.first_last(line)).collect();
+    Calibration { lines, matches }
+}
+
+fn part1(filename: &str) -> Result<Calibration> {
+    Ok(calibrate(
+        read_lines(filename)?,
+        &Vocabulary::digits().compile(),
+    ))
+}
+
+fn part2(filename: &str, vocabulary: &Vocabulary) -> Result<Calibration> {
+    Ok(calibrate(read_lines(filename)?, &vocabulary.compile()))
+}
+
+// Lines without a digit add nothing to the sum. They are reported, or an error
+// in strict mode.
+fn report(part: &str, calibration: &Calibration, strict: bool, explain: bool) -> Result<()> {
+    if explain {
+        print!("{}", calibration.explain());
+    }
+    let missing = calibration.missing();
+    if !missing.is_empty() {
+        let lines = missing.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let message = format!("{part}: no digits on line {}", lines.join(", "));
+        if strict {
+            bail!(message);
         }
+        println!("{message}");
     }
-    Ok(sum)
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    let filename = env::args().nth(1).unwrap_or_else(|| "inputs/input.txt".to_string());
+    let mut filename = "inputs/input.txt".to_string();
+    let mut vocabulary = Vocabulary::english();
+    let mut strict = false;
+    let mut explain = false;
+    let mut bench = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // word=digit per line, replacing the English words for part 2
+            "--vocab" => {
+                vocabulary = Vocabulary::load(&args.next().context("--vocab needs a filename")?)?
+            }
+            "--strict" => strict = true,
+            "--explain" => explain = true,
+            // size in megabytes of the generated document, e.g. --bench 16
+            "--bench" => bench = Some(args.next().context("--bench needs a size")?.parse()?),
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
+        }
+    }
+
+    if let Some(megabytes) = bench {
+        return bench::run(megabytes);
+    }
+
     let start1 = Instant::now();
-    let sum1 = part1(&filename).expect("failed");
+    let calibration1 = part1(&filename)?;
+    let sum1 = calibration1.sum();
     let duration1 = start1.elapsed();
+    report("part1", &calibration1, strict, explain)?;
     println!("part1: {sum1}, time: {duration1:?}");
     let start2 = Instant::now();
-    let sum2 = part2(&filename).expect("failed");
+    let calibration2 = part2(&filename, &vocabulary)?;
+    let sum2 = calibration2.sum();
     let duration2 = start2.elapsed();
+    report("part2", &calibration2, strict, explain)?;
     println!("part2: {sum2}, time: {duration2:?}");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        assert_eq!(142, part1("src/inputs/test1.txt").unwrap().sum());
+        assert_eq!(
+            281,
+            part2("src/inputs/test2.txt", &Vocabulary::english())
+                .unwrap()
+                .sum()
+        );
+    }
+
+    #[test]
+    fn test_missing_digits() {
+        // eightwothree has no digit, and the trailing blank line is skipped
+        let calibration = part1("src/inputs/test2.txt").unwrap();
+        assert_eq!(vec![2], calibration.missing());
+        assert!(calibration
+            .explain()
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with("no digits"));
+        assert!(report("part1", &calibration, true, false).is_err());
+        assert!(report("part1", &calibration, false, false).is_ok());
+
+        let calibration = part2("src/inputs/test2.txt", &Vocabulary::english()).unwrap();
+        assert!(calibration.missing().is_empty());
+        let explained = calibration.explain();
+        assert!(explained.contains("     2  eightwothree"));
+        assert!(explained.contains("83 = eight + three\n"));
+        assert_eq!(7, explained.lines().count());
+    }
+
+    #[test]
+    fn test_solution() {
+        let filename = "src/inputs/input.txt";
+        assert_eq!(54388, part1(filename).unwrap().sum());
+        assert_eq!(
+            53515,
+            part2(filename, &Vocabulary::english()).unwrap().sum()
+        );
+    }
+}
@@ -0,0 +1,305 @@
+// Words that stand for digits, compiled into Aho-Corasick automata that find
+// every occurrence of every word, overlapping ones included ("twone" has both
+// "two" and "one").
+
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+
+const ENGLISH: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vocabulary {
+    words: Vec<(String, u32)>,
+}
+
+impl Vocabulary {
+    // Only the digits themselves
+    pub fn digits() -> Self {
+        Vocabulary { words: Vec::new() }
+    }
+
+    pub fn english() -> Self {
+        let words = ENGLISH
+            .iter()
+            .zip(0..)
+            .map(|(word, digit)| (word.to_string(), digit))
+            .collect();
+        Vocabulary { words }
+    }
+
+    // One word=digit per line, e.g. "uno=1". Blank lines and lines starting
+    // with # are ignored.
+    pub fn parse(config: &str) -> Result<Self> {
+        let mut words: Vec<(String, u32)> = Vec::new();
+        for (i, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let context = || format!("line {}", i + 1);
+            let (word, digit) = line
+                .split_once('=')
+                .context("expected word=digit")
+                .with_context(context)?;
+            let word = word.trim();
+            let digit = digit.trim().parse::<u32>().with_context(context)?;
+            if word.is_empty() {
+                bail!("line {}: empty word", i + 1);
+            }
+            if digit > 9 {
+                bail!("line {}: {digit} is not a digit", i + 1);
+            }
+            // the digits already match as themselves, see compile
+            if word.len() == 1 && word.as_bytes()[0].is_ascii_digit() {
+                bail!("line {}: {word} can't be redefined", i + 1);
+            }
+            match words.iter().find(|(w, _)| w == word) {
+                Some((_, d)) if *d != digit => bail!("line {}: {word} is already {d}", i + 1),
+                Some(_) => (),
+                None => words.push((word.to_string(), digit)),
+            }
+        }
+        Ok(Vocabulary { words })
+    }
+
+    pub fn load(filename: &str) -> Result<Self> {
+        Self::parse(&fs::read_to_string(filename)?).with_context(|| filename.to_string())
+    }
+
+    // The digits 0-9 always match as themselves
+    pub fn compile(&self) -> Matcher {
+        let digits = ('0'..='9').map(|c| c.to_string()).zip(0..);
+        let words: Vec<_> = digits.chain(self.words.iter().cloned()).collect();
+        Matcher::new(&words)
+    }
+}
+
+// Trie node while building the automaton
+#[derive(Debug, Default)]
+struct Node {
+    next: HashMap<u8, usize>,
+    // (length in bytes, digit) of every word ending here
+    outputs: Vec<(usize, u32)>,
+}
+
+// A match: byte offset where the word starts, its length in bytes and digit.
+// Words are valid UTF-8 so in UTF-8 text these always fall on char boundaries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match {
+    pub start: usize,
+    pub len: usize,
+    pub digit: u32,
+}
+
+// An Aho-Corasick automaton as a DFA over bytes: one table lookup per input
+// byte, with the fail links already folded into the transitions
+#[derive(Debug)]
+struct Dfa {
+    transitions: Vec<[u32; 256]>,
+    // (length, digit) of the words ending at each state, including those
+    // reached through fail links
+    outputs: Vec<Vec<(usize, u32)>>,
+}
+
+#[derive(Debug)]
+struct Builder {
+    nodes: Vec<Node>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder {
+            nodes: vec![Node::default()],
+        }
+    }
+}
+
+impl Builder {
+    fn insert(&mut self, word: impl Iterator<Item = u8>, digit: u32) {
+        let mut node = 0;
+        let mut len = 0;
+        for b in word {
+            node = match self.nodes[node].next.get(&b) {
+                Some(&next) => next,
+                None => {
+                    self.nodes.push(Node::default());
+                    let next = self.nodes.len() - 1;
+                    self.nodes[node].next.insert(b, next);
+                    next
+                }
+            };
+            len += 1;
+        }
+        self.nodes[node].outputs.push((len, digit));
+    }
+
+    // Breadth first, so a node's fail state (which is shallower) already has
+    // its transitions and outputs complete when the node is reached
+    fn build(self) -> Dfa {
+        let n = self.nodes.len();
+        let mut transitions = vec![[0u32; 256]; n];
+        let mut outputs: Vec<_> = self.nodes.iter().map(|node| node.outputs.clone()).collect();
+        let mut fail = vec![0; n];
+        let mut queue = VecDeque::new();
+        for (&b, &child) in &self.nodes[0].next {
+            transitions[0][b as usize] = child as u32;
+            queue.push_back(child);
+        }
+        while let Some(node) = queue.pop_front() {
+            transitions[node] = transitions[fail[node]];
+            for (&b, &child) in &self.nodes[node].next {
+                fail[child] = transitions[fail[node]][b as usize] as usize;
+                let inherited = outputs[fail[child]].clone();
+                outputs[child].extend(inherited);
+                transitions[node][b as usize] = child as u32;
+                queue.push_back(child);
+            }
+        }
+        Dfa {
+            transitions,
+            outputs,
+        }
+    }
+}
+
+// Finds the first match scanning forward and the last scanning backward with
+// the words reversed. Each stops as soon as its answer can't change, so a line
+// is read about once in total, and both work on any bytes, valid UTF-8 or not.
+#[derive(Debug)]
+pub struct Matcher {
+    forward: Dfa,
+    backward: Dfa,
+    max_len: usize,
+}
+
+impl Matcher {
+    fn new(words: &[(String, u32)]) -> Self {
+        let mut forward = Builder::default();
+        let mut backward = Builder::default();
+        for (word, digit) in words {
+            forward.insert(word.bytes(), *digit);
+            backward.insert(word.bytes().rev(), *digit);
+        }
+        Matcher {
+            forward: forward.build(),
+            backward: backward.build(),
+            max_len: words.iter().map(|(word, _)| word.len()).max().unwrap_or(0),
+        }
+    }
+
+    // Leftmost match, the longest if several start there. Matches come up in
+    // order of where they end, so keep going until the next one to end can't
+    // start early enough.
+    fn first(&self, s: &[u8]) -> Option<Match> {
+        let mut first: Option<Match> = None;
+        let mut state = 0;
+        for (i, &b) in s.iter().enumerate() {
+            state = self.forward.transitions[state][b as usize] as usize;
+            for &(len, digit) in &self.forward.outputs[state] {
+                let m = Match {
+                    start: i + 1 - len,
+                    len,
+                    digit,
+                };
+                let better = |f: Match| m.start < f.start || (m.start == f.start && m.len > f.len);
+                if first.is_none_or(better) {
+                    first = Some(m);
+                }
+            }
+            if first.is_some_and(|f| i + 1 >= f.start + self.max_len) {
+                break;
+            }
+        }
+        first
+    }
+
+    // Rightmost match, the longest if several start there. Scanning backward
+    // the first match found has the rightmost start.
+    fn last(&self, s: &[u8]) -> Option<Match> {
+        let mut state = 0;
+        for (i, &b) in s.iter().enumerate().rev() {
+            state = self.backward.transitions[state][b as usize] as usize;
+            let outputs = &self.backward.outputs[state];
+            if let Some(&(len, digit)) = outputs.iter().max_by_key(|(len, _)| *len) {
+                return Some(Match {
+                    start: i,
+                    len,
+                    digit,
+                });
+            }
+        }
+        None
+    }
+
+    // Leftmost and rightmost match. When several start at the same place the
+    // longest wins. Words overlapping a longer one but starting later still
+    // count, so the last match can lie inside the first ("bc" in "abcd").
+    pub fn first_last(&self, s: &[u8]) -> Option<(Match, Match)> {
+        let first = self.first(s)?;
+        // the last match is at or after the first, so it is there to be found
+        Some((first, self.last(s)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digits(matcher: &Matcher, s: &str) -> Option<(u32, u32)> {
+        matcher
+            .first_last(s.as_bytes())
+            .map(|(first, last)| (first.digit, last.digit))
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let matcher = Vocabulary::english().compile();
+        assert_eq!(Some((2, 1)), digits(&matcher, "twone"));
+        assert_eq!(Some((8, 3)), digits(&matcher, "eightwothree"));
+        assert_eq!(Some((1, 8)), digits(&matcher, "zoneight"));
+        assert_eq!(Some((7, 6)), digits(&matcher, "7pqrstsixteen"));
+        assert_eq!(None, digits(&matcher, "abc"));
+        assert_eq!(
+            Some((1, 1)),
+            digits(&Vocabulary::digits().compile(), "two1nine")
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let vocabulary = Vocabulary::parse("# spanish\nuno=1\n\ndos = 2\nnueve=9\n").unwrap();
+        let matcher = vocabulary.compile();
+        assert_eq!(Some((2, 1)), digits(&matcher, "xdosunox"));
+        assert_eq!(Some((9, 1)), digits(&matcher, "nueve8uno"));
+        // "one" isn't in this vocabulary
+        assert_eq!(None, digits(&matcher, "one"));
+        // a word inside a longer word starting earlier is still the last match
+        let matcher = Vocabulary::parse("abcd=1\nbc=2").unwrap().compile();
+        assert_eq!(Some((1, 2)), digits(&matcher, "abcd"));
+        // non-ASCII words and text
+        let matcher = Vocabulary::parse("zwei=2\nдва=2\nein=1").unwrap().compile();
+        assert_eq!(Some((2, 1)), digits(&matcher, "äzweiдваein"));
+        assert_eq!(Some((2, 1)), digits(&matcher, "два€ein"));
+        // multi-byte characters sharing a first byte with a word don't match
+        assert_eq!(None, digits(&matcher, "дв¾д"));
+        // invalid UTF-8 is fine too
+        assert_eq!(
+            Some((2, 1)),
+            matcher
+                .first_last(b"\xff\xd0zwei\xd0\xb4\xc0ein\x80")
+                .map(|(first, last)| (first.digit, last.digit))
+        );
+
+        assert!(Vocabulary::parse("one").is_err());
+        assert!(Vocabulary::parse("one=10").is_err());
+        assert!(Vocabulary::parse("=1").is_err());
+        assert!(Vocabulary::parse("one=1\none=2").is_err());
+        // "x7x" would read as 7 forward and 2 backward
+        assert!(Vocabulary::parse("7=2").is_err());
+        assert!(Vocabulary::parse("7=7").is_err());
+        assert!(Vocabulary::parse("77=2").is_ok());
+    }
+}
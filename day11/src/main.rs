@@ -1,72 +1,364 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs;
+use std::str::FromStr;
 use std::time::Instant;
 
-fn parse_and_solve(filename: &str, expansion: usize) -> Result<usize> {
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
+mod render;
 
-    let mut galaxies = Vec::new();
-    let mut col_multiplier = Vec::new();
-    let mut row_multiplier = Vec::new();
+// Sum of |a - b| over all pairs. Once sorted each coordinate is the larger one
+// of its pairs with all the coordinates before it, so a running sum of those
+// gives the total in O(n log n). None if it doesn't fit a usize.
+fn sum_pairwise_distances(mut coords: Vec<usize>) -> Option<usize> {
+    coords.sort_unstable();
+    let mut sum: usize = 0;
+    let mut preceding: usize = 0;
+    for (i, c) in coords.into_iter().enumerate() {
+        sum = sum.checked_add(c.checked_mul(i)? - preceding)?;
+        preceding = preceding.checked_add(c)?;
+    }
+    Some(sum)
+}
+
+// Galaxy positions in the unexpanded image along with, for each row and
+// column, how many empty ones come before it. An expansion factor then only
+// changes how far apart those are, so one parse serves any factor.
+#[derive(Debug)]
+struct GalaxyMap {
+    galaxies: Vec<(usize, usize)>,
+    empty_rows: Vec<bool>,
+    empty_cols: Vec<bool>,
+    empty_rows_before: Vec<usize>,
+    empty_cols_before: Vec<usize>,
+}
+
+impl FromStr for GalaxyMap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut galaxies = Vec::new();
+        let mut empty_rows = Vec::new();
+        let mut empty_cols = Vec::new();
+
+        for (i, row) in s.lines().enumerate() {
+            if i == 0 {
+                empty_cols = vec![true; row.len()];
+            } else if row.len() != empty_cols.len() {
+                bail!("line {}: expected {} columns", i + 1, empty_cols.len());
+            }
+            let mut empty_row = true;
+            for (j, v) in row.bytes().enumerate() {
+                match v {
+                    b'#' => {
+                        galaxies.push((i, j));
+                        empty_row = false;
+                        empty_cols[j] = false;
+                    }
+                    b'.' => (),
+                    _ => bail!("line {}: unexpected {:?}", i + 1, v as char),
+                }
+            }
+            empty_rows.push(empty_row);
+        }
+        Ok(GalaxyMap {
+            galaxies,
+            empty_rows_before: count_before(&empty_rows),
+            empty_cols_before: count_before(&empty_cols),
+            empty_rows,
+            empty_cols,
+        })
+    }
+}
+
+// Number of true values before each index
+fn count_before(empty: &[bool]) -> Vec<usize> {
+    empty
+        .iter()
+        .scan(0, |count, &e| {
+            let c = *count;
+            *count += e as usize;
+            Some(c)
+        })
+        .collect()
+}
+
+impl GalaxyMap {
+    // Position of a row or column once every empty one is replaced by
+    // `expansion` of them. Any factor is allowed, but past the usize range
+    // this is an error.
+    fn expand(index: usize, empty_before: usize, expansion: usize) -> Result<usize> {
+        empty_before
+            .checked_mul(expansion)
+            .and_then(|empty| empty.checked_add(index - empty_before))
+            .with_context(|| format!("expansion {expansion} is too large"))
+    }
+
+    // Size of the image after expansion, in rows and columns
+    fn expanded_size(&self, expansion: usize) -> Result<(usize, usize)> {
+        let size = |empty: &[bool]| {
+            let count = empty.iter().filter(|&&e| e).count();
+            Self::expand(empty.len(), count, expansion)
+        };
+        Ok((size(&self.empty_rows)?, size(&self.empty_cols)?))
+    }
+
+    fn expanded(&self, galaxy: usize, expansion: usize) -> Result<(usize, usize)> {
+        let (i, j) = self.galaxies[galaxy];
+        Ok((
+            Self::expand(i, self.empty_rows_before[i], expansion)?,
+            Self::expand(j, self.empty_cols_before[j], expansion)?,
+        ))
+    }
+
+    // Shortest path length between galaxies a and b (0 based, in reading order)
+    fn pair_distance(&self, a: usize, b: usize, expansion: usize) -> Result<usize> {
+        let (ai, aj) = self.expanded(a, expansion)?;
+        let (bi, bj) = self.expanded(b, expansion)?;
+        ai.abs_diff(bi)
+            .checked_add(aj.abs_diff(bj))
+            .with_context(|| format!("expansion {expansion} is too large"))
+    }
+
+    // Sum of the shortest paths between every pair of galaxies. Manhattan
+    // distance splits into independent row and column sums.
+    fn total_distance(&self, expansion: usize) -> Result<usize> {
+        let (rows, cols) = (0..self.galaxies.len())
+            .map(|g| self.expanded(g, expansion))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        sum_pairwise_distances(rows)
+            .zip(sum_pairwise_distances(cols))
+            .and_then(|(rows, cols)| rows.checked_add(cols))
+            .with_context(|| format!("expansion {expansion} is too large"))
+    }
 
-    for (i, line) in reader.lines().enumerate() {
-        let row = line?;
-        let mut empty_row = true;
-        if col_multiplier.is_empty() {
-            col_multiplier = vec![expansion; row.len()];
+    // Index of the referenced galaxy
+    fn find(&self, galaxy: &GalaxyRef) -> Result<usize> {
+        match *galaxy {
+            GalaxyRef::Number(n) if (1..=self.galaxies.len()).contains(&n) => Ok(n - 1),
+            GalaxyRef::Number(n) => bail!("no galaxy {n}, there are {}", self.galaxies.len()),
+            // galaxies are stored in reading order
+            GalaxyRef::At(i, j) => self
+                .galaxies
+                .binary_search(&(i, j))
+                .map_err(|_| anyhow!("no galaxy at {i}:{j}")),
         }
-        for (j, v) in row.bytes().enumerate() {
-            if v == b'#' {
-                galaxies.push((i, j));
-                empty_row = false;
-                col_multiplier[j] = 1;
+    }
+
+    // Closest and farthest other galaxy, lowest index first on ties. None if
+    // there is no other galaxy.
+    fn neighbours(&self, galaxy: usize, expansion: usize) -> Result<Option<Neighbours>> {
+        let others = (0..self.galaxies.len())
+            .filter(|&g| g != galaxy)
+            .map(|g| Ok((g, self.pair_distance(galaxy, g, expansion)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut others = others.into_iter();
+        let Some(first) = others.next() else {
+            return Ok(None);
+        };
+        let (nearest, farthest) = others.fold((first, first), |(near, far), other| {
+            (
+                if other.1 < near.1 { other } else { near },
+                if other.1 > far.1 { other } else { far },
+            )
+        });
+        Ok(Some(Neighbours { nearest, farthest }))
+    }
+
+    // Number of galaxy pairs at each distance. Quadratic in the number of
+    // galaxies, unlike total_distance.
+    fn distance_histogram(&self, expansion: usize) -> Result<BTreeMap<usize, usize>> {
+        let mut histogram = BTreeMap::new();
+        for a in 0..self.galaxies.len() {
+            for b in a + 1..self.galaxies.len() {
+                *histogram
+                    .entry(self.pair_distance(a, b, expansion)?)
+                    .or_insert(0) += 1;
             }
         }
-        let row_expansion = if empty_row { expansion } else { 1 };
-        row_multiplier.push(row_expansion);
-    }
-    let mut sum = 0;
-    for (i, p1) in galaxies.iter().enumerate() {
-        for p2 in &galaxies[i + 1..] {
-            let xrange = if p1.0 < p2.0 { p1.0..p2.0 } else { p2.0..p1.0 };
-            for x in xrange {
-                sum += row_multiplier[x];
+        Ok(histogram)
+    }
+}
+
+// A galaxy given by its number in reading order, counted from 1 as in the
+// puzzle text ("5"), or by its row and column in the unexpanded image ("5:1")
+#[derive(Debug, PartialEq)]
+enum GalaxyRef {
+    Number(usize),
+    At(usize, usize),
+}
+
+impl FromStr for GalaxyRef {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.split_once(':') {
+            Some((i, j)) => GalaxyRef::At(i.trim().parse()?, j.trim().parse()?),
+            None => GalaxyRef::Number(s.trim().parse()?),
+        })
+    }
+}
+
+// (galaxy index, distance) of the closest and farthest other galaxy
+#[derive(Debug, PartialEq)]
+struct Neighbours {
+    nearest: (usize, usize),
+    farthest: (usize, usize),
+}
+
+fn parse_galaxy_map(filename: &str) -> Result<GalaxyMap> {
+    fs::read_to_string(filename)?.parse()
+}
+
+fn part1(map: &GalaxyMap) -> Result<usize> {
+    map.total_distance(2)
+}
+
+fn part2(map: &GalaxyMap) -> Result<usize> {
+    map.total_distance(1_000_000)
+}
+
+// day11 query [FILE] [--expansion N] [--pair A,B]... [--neighbours] [--histogram]
+// where A and B are galaxy numbers or row:col positions, e.g. --pair 5,9:4
+fn query(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let mut filename = "inputs/test1.txt".to_string();
+    let mut expansion = 2;
+    let mut pairs = Vec::new();
+    let mut neighbours = false;
+    let mut histogram = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--expansion" => {
+                expansion = args.next().context("--expansion needs a factor")?.parse()?
             }
-            let yrange = if p1.1 < p2.1 { p1.1..p2.1 } else { p2.1..p1.1 };
-            for y in yrange {
-                sum += col_multiplier[y];
+            "--pair" => {
+                let pair = args.next().context("--pair needs two galaxies")?;
+                let (a, b) = pair.split_once(',').context("--pair expects A,B")?;
+                pairs.push((a.parse::<GalaxyRef>()?, b.parse::<GalaxyRef>()?));
             }
+            "--neighbours" => neighbours = true,
+            "--histogram" => histogram = true,
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
         }
     }
-    Ok(sum)
-}
+    if pairs.is_empty() && !neighbours && !histogram {
+        bail!("query needs --pair, --neighbours or --histogram");
+    }
+
+    let map = parse_galaxy_map(&filename)?;
+    println!("expansion {expansion}");
 
-fn part1(filename: &str) -> Result<usize> {
-    parse_and_solve(filename, 2)
+    for (a, b) in &pairs {
+        let (ga, gb) = (map.find(a)?, map.find(b)?);
+        let distance = map.pair_distance(ga, gb, expansion)?;
+        println!("{} to {}: {distance}", ga + 1, gb + 1);
+    }
+
+    if neighbours {
+        println!(
+            "{:>7} {:>9} {:>8} {:>9} {:>9} {:>9}",
+            "galaxy", "at", "nearest", "distance", "farthest", "distance"
+        );
+        for (g, &(i, j)) in map.galaxies.iter().enumerate() {
+            let at = format!("{i}:{j}");
+            match map.neighbours(g, expansion)? {
+                Some(Neighbours { nearest, farthest }) => println!(
+                    "{:>7} {at:>9} {:>8} {:>9} {:>9} {:>9}",
+                    g + 1,
+                    nearest.0 + 1,
+                    nearest.1,
+                    farthest.0 + 1,
+                    farthest.1
+                ),
+                None => println!("{:>7} {at:>9} {:>8}", g + 1, "-"),
+            }
+        }
+    }
+
+    if histogram {
+        println!("{:>8} {:>8}", "distance", "pairs");
+        for (distance, count) in map.distance_histogram(expansion)? {
+            println!("{distance:>8} {count:>8}");
+        }
+    }
+    Ok(())
 }
 
-fn part2(filename: &str) -> Result<usize> {
-    parse_and_solve(filename, 1_000_000)
+// day11 render [FILE] [--expansion N] [--svg OUT]
+fn render(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let mut filename = "inputs/test1.txt".to_string();
+    let mut expansion = 2;
+    let mut svg = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--expansion" => {
+                expansion = args.next().context("--expansion needs a factor")?.parse()?
+            }
+            "--svg" => svg = Some(args.next().context("--svg needs a filename")?),
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
+        }
+    }
+
+    let map = parse_galaxy_map(&filename)?;
+    match svg {
+        Some(svg) => {
+            fs::write(&svg, render::svg(&map, expansion)?)?;
+            println!("universe written to {svg}");
+        }
+        None => print!("{}", render::text(&map, expansion)?),
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    let filename = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "inputs/test1.txt".to_string());
+    let mut filename = "inputs/test1.txt".to_string();
+    let mut expansions = Vec::new();
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("query") => return query(args.skip(1)),
+        Some("render") => return render(args.skip(1)),
+        _ => (),
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // e.g. --expansion 2,10,100 --expansion 1000
+            "--expansion" => {
+                let factors = args.next().context("--expansion needs a factor")?;
+                for factor in factors.split(',') {
+                    expansions.push(factor.trim().parse::<usize>()?);
+                }
+            }
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
+        }
+    }
 
     let start = Instant::now();
-    let sum1 = part1(&filename).unwrap();
+    let map = parse_galaxy_map(&filename)?;
+    let duration = start.elapsed();
+    println!("parsing time: {duration:?}");
+
+    let start = Instant::now();
+    let sum1 = part1(&map)?;
     let duration1 = start.elapsed();
     println!("part1: {sum1}, time: {duration1:?}");
 
     let start = Instant::now();
-    let sum2 = part2(&filename).unwrap();
+    let sum2 = part2(&map)?;
     let duration2 = start.elapsed();
     println!("part2: {sum2}, time: {duration2:?}");
 
+    for expansion in expansions {
+        let start = Instant::now();
+        let sum = map.total_distance(expansion)?;
+        let duration = start.elapsed();
+        println!("expansion {expansion}: {sum}, time: {duration:?}");
+    }
+
     Ok(())
 }
 
@@ -76,17 +368,79 @@ mod tests {
 
     #[test]
     fn test_sample() {
-        let filename = "src/inputs/test1.txt";
+        let map = parse_galaxy_map("src/inputs/test1.txt").unwrap();
 
-        assert_eq!(374, part1(&filename).unwrap());
-        assert_eq!(82000210, part2(&filename).unwrap());
+        assert_eq!(374, part1(&map).unwrap());
+        assert_eq!(82000210, part2(&map).unwrap());
+        assert_eq!(1030, map.total_distance(10).unwrap());
+        assert_eq!(8410, map.total_distance(100).unwrap());
+        // galaxies 5 and 9 of the puzzle text
+        assert_eq!(9, map.pair_distance(4, 8, 2).unwrap());
+        assert_eq!(15, map.pair_distance(0, 6, 2).unwrap());
+        assert_eq!(17, map.pair_distance(2, 5, 2).unwrap());
+        assert_eq!(5, map.pair_distance(7, 8, 2).unwrap());
+    }
+
+    #[test]
+    fn test_queries() {
+        let map = parse_galaxy_map("src/inputs/test1.txt").unwrap();
+
+        assert_eq!(4, map.find(&"5".parse().unwrap()).unwrap());
+        assert_eq!(4, map.find(&"5:1".parse().unwrap()).unwrap());
+        assert!(map.find(&GalaxyRef::Number(10)).is_err());
+        assert!(map.find(&GalaxyRef::At(0, 0)).is_err());
+
+        // galaxy 9 is 5 from both galaxy 7 and galaxy 8, the lower one wins
+        let neighbours = map.neighbours(8, 2).unwrap().unwrap();
+        assert_eq!((6, 5), neighbours.nearest);
+        assert_eq!(5, map.pair_distance(8, 7, 2).unwrap());
+        let farthest = (0..9)
+            .map(|g| map.pair_distance(8, g, 2).unwrap())
+            .max()
+            .unwrap();
+        assert_eq!(farthest, neighbours.farthest.1);
+
+        let histogram = map.distance_histogram(2).unwrap();
+        assert_eq!(36, histogram.values().sum::<usize>());
+        assert_eq!(374, histogram.iter().map(|(d, n)| d * n).sum::<usize>());
+    }
+
+    #[test]
+    fn test_sum_pairwise_distances() {
+        assert_eq!(Some(0), sum_pairwise_distances(vec![]));
+        assert_eq!(Some(0), sum_pairwise_distances(vec![7]));
+        // |5-1| + |5-3| + |5-5| + |1-3| + |1-5| + |3-5|
+        assert_eq!(Some(14), sum_pairwise_distances(vec![5, 1, 3, 5]));
+        assert_eq!(
+            None,
+            sum_pairwise_distances(vec![0, usize::MAX, usize::MAX])
+        );
+        assert_eq!(vec![0, 0, 1, 1], count_before(&[false, true, false, false]));
     }
 
     #[test]
     fn test_solution() {
-        let filename = "src/inputs/input.txt";
+        let map = parse_galaxy_map("src/inputs/input.txt").unwrap();
 
-        assert_eq!(9543156, part1(filename).unwrap());
-        assert_eq!(625243292686, part2(filename).unwrap());
+        assert_eq!(9543156, part1(&map).unwrap());
+        assert_eq!(625243292686, part2(&map).unwrap());
+    }
+
+    #[test]
+    fn test_huge_expansion() {
+        let map = parse_galaxy_map("src/inputs/input.txt").unwrap();
+        // 10^14 still fits in a u64, the sum over all pairs doesn't
+        assert!(map.total_distance(100_000_000_000_000).is_err());
+        assert!(map.expanded_size(usize::MAX).is_err());
+        assert!(map.pair_distance(0, 1, usize::MAX).is_err());
+        assert!(map.neighbours(0, usize::MAX).is_err());
+        assert!(map.distance_histogram(usize::MAX).is_err());
+        assert!(render::text(&map, usize::MAX).is_err());
+        assert!(render::svg(&map, usize::MAX).is_err());
+        // too large to draw but the distances are fine
+        let map = parse_galaxy_map("src/inputs/test1.txt").unwrap();
+        assert!(render::svg(&map, usize::MAX / 20).is_err());
+        assert!(map.total_distance(usize::MAX / 20).is_err());
+        assert!(map.pair_distance(0, 8, usize::MAX / 20).is_ok());
     }
 }
@@ -0,0 +1,144 @@
+// Pictures of the universe after expansion, with galaxies numbered in reading
+// order as in the puzzle text
+
+use super::GalaxyMap;
+use anyhow::{bail, Result};
+
+// Largest expanded image, in rows or columns, that still makes sense as text
+const MAX_TEXT_SIZE: usize = 1000;
+// Side of one expanded cell in SVG units
+const CELL: usize = 10;
+
+// Each expanded row and column repeated from the original, with the original
+// index and whether it was empty
+fn expanded_lines(empty: &[bool], expansion: usize) -> Vec<(usize, bool)> {
+    empty
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &e)| std::iter::repeat_n((i, e), if e { expansion } else { 1 }))
+        .collect()
+}
+
+// Marks empty columns with v above them and empty rows with > and <, the way
+// the puzzle text does. Cells are widened to fit the largest galaxy number.
+pub fn text(map: &GalaxyMap, expansion: usize) -> Result<String> {
+    let (rows, cols) = map.expanded_size(expansion)?;
+    if rows.max(cols) > MAX_TEXT_SIZE {
+        bail!("{rows}x{cols} is too large to render as text, try --svg");
+    }
+    let width = map.galaxies.len().max(1).to_string().len();
+    let cols = expanded_lines(&map.empty_cols, expansion);
+
+    let mut header = String::from(" ");
+    for &(_, empty) in &cols {
+        header += &format!("{:>width$}", if empty { "v" } else { "" });
+    }
+    let mut out = header.trim_end().to_string() + "\n";
+
+    // galaxies are in reading order, so they come up one after the other
+    let mut galaxies = map.galaxies.iter().enumerate().peekable();
+    for (i, empty) in expanded_lines(&map.empty_rows, expansion) {
+        out.push(if empty { '>' } else { ' ' });
+        for &(j, _) in &cols {
+            match galaxies.next_if(|(_, &at)| at == (i, j)) {
+                Some((g, _)) => out += &format!("{:.>width$}", g + 1),
+                None => out += &".".repeat(width),
+            }
+        }
+        out += if empty { "<\n" } else { "\n" };
+    }
+    Ok(out)
+}
+
+// Empty rows and columns are shaded bands, galaxies labelled dots
+pub fn svg(map: &GalaxyMap, expansion: usize) -> Result<String> {
+    let (rows, cols) = map.expanded_size(expansion)?;
+    // every position in the picture is within its width and height
+    let (width, height) = match (cols.checked_mul(CELL), rows.checked_mul(CELL)) {
+        (Some(width), Some(height)) => (width, height),
+        _ => bail!("{rows}x{cols} is too large to render"),
+    };
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {width} {height}\" \
+         width=\"{width}\" height=\"{height}\">\n"
+    );
+    svg += "  <rect width=\"100%\" height=\"100%\" fill=\"black\"/>\n";
+
+    let band = "fill=\"steelblue\" fill-opacity=\"0.4\"";
+    for (i, &empty) in map.empty_rows.iter().enumerate() {
+        if empty {
+            let y = GalaxyMap::expand(i, map.empty_rows_before[i], expansion)? * CELL;
+            let h = expansion * CELL;
+            svg +=
+                &format!("  <rect x=\"0\" y=\"{y}\" width=\"{width}\" height=\"{h}\" {band}/>\n");
+        }
+    }
+    for (j, &empty) in map.empty_cols.iter().enumerate() {
+        if empty {
+            let x = GalaxyMap::expand(j, map.empty_cols_before[j], expansion)? * CELL;
+            let w = expansion * CELL;
+            svg +=
+                &format!("  <rect x=\"{x}\" y=\"0\" width=\"{w}\" height=\"{height}\" {band}/>\n");
+        }
+    }
+
+    for g in 0..map.galaxies.len() {
+        let (i, j) = map.expanded(g, expansion)?;
+        let (x, y) = (j * CELL + CELL / 2, i * CELL + CELL / 2);
+        svg += &format!("  <circle cx=\"{x}\" cy=\"{y}\" r=\"3\" fill=\"gold\"/>\n");
+        svg += &format!(
+            "  <text x=\"{}\" y=\"{y}\" font-size=\"6\" fill=\"white\">{}</text>\n",
+            x + 4,
+            g + 1
+        );
+    }
+    svg += "</svg>\n";
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_galaxy_map;
+
+    #[test]
+    fn test_text() {
+        let map = parse_galaxy_map("src/inputs/test1.txt").unwrap();
+        let expected = "   vv  vv  vv
+ ....1........
+ .........2...
+ 3............
+>.............<
+>.............<
+ ........4....
+ .5...........
+ ............6
+>.............<
+>.............<
+ .........7...
+ 8....9.......
+";
+        assert_eq!(expected, text(&map, 2).unwrap());
+        assert!(text(&map, 1_000_000).is_err());
+
+        // numbers past 9 get wider cells
+        let map: GalaxyMap = "#.#.#.#.#.#.#.#.#.#".parse().unwrap();
+        let out = text(&map, 1).unwrap();
+        assert!(out.ends_with("\n .1...2...3...4...5...6...7...8...9..10\n"));
+    }
+
+    #[test]
+    fn test_svg() {
+        let map = parse_galaxy_map("src/inputs/test1.txt").unwrap();
+        let svg = svg(&map, 2).unwrap();
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 130 120\"")
+        );
+        // empty row 3 doubled, just after the 3 rows before it
+        assert!(svg.contains("<rect x=\"0\" y=\"30\" width=\"130\" height=\"20\""));
+        // galaxy 1 at row 0, column 3 + 1 empty column before it
+        assert!(svg.contains("<circle cx=\"45\" cy=\"5\""));
+        assert_eq!(9, svg.matches("<circle").count());
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
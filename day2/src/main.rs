@@ -1,101 +1,407 @@
-use anyhow::{Error, Result};
+use anyhow::{bail, Context, Error, Result};
 use std::cmp::max;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 use std::time::Instant;
 
-type CubeSet = [u32; 3];
+const CUBE_BAG: &str = "red=12,green=13,blue=14"; // Default bag
 
-fn parse_cubeset(cubeset_str: &str) -> Result<CubeSet> {
-    let mut cubeset = [0, 0, 0];
-    const COLORS: [&str; 3] = ["red", "green", "blue"];
-    for num_and_cube in cubeset_str.split(',') {
-        let (num, color) = num_and_cube[1..]
-            .split_once(' ')
-            .ok_or_else(|| Error::msg("invalid cube configuration"))?;
-        let pos = COLORS
-            .iter()
-            .position(|c| c == &color)
-            .ok_or_else(|| Error::msg("invalid color"))?;
-        cubeset[pos] = num.parse::<u32>()?;
+// Number of cubes of each color. Colors are whatever the input names, a color
+// that isn't there has no cubes.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CubeSet(BTreeMap<String, u32>);
+
+impl CubeSet {
+    fn get(&self, color: &str) -> u32 {
+        self.0.get(color).copied().unwrap_or(0)
+    }
+
+    fn insert(&mut self, color: &str, num: u32) -> Result<()> {
+        if color.is_empty() || color.contains(char::is_whitespace) {
+            bail!("invalid color {color:?}");
+        }
+        if self.0.insert(color.to_string(), num).is_some() {
+            bail!("{color} given twice");
+        }
+        Ok(())
+    }
+
+    // Whether everything in self could be taken out of bag
+    fn fits_in(&self, bag: &CubeSet) -> bool {
+        self.0.iter().all(|(color, &num)| num <= bag.get(color))
+    }
+
+    // Format: "3 blue, 4 red"
+    fn parse_draw(cubeset_str: &str) -> Result<Self> {
+        let mut cubeset = CubeSet::default();
+        for num_and_cube in cubeset_str.split(',') {
+            let (num, color) = num_and_cube
+                .trim()
+                .split_once(' ')
+                .ok_or_else(|| Error::msg("invalid cube configuration"))?;
+            cubeset.insert(color.trim(), num.parse::<u32>()?)?;
+        }
+        Ok(cubeset)
     }
-    Ok(cubeset)
 }
 
-fn part1(filename: &str) -> Result<u32> {
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
-    let mut sum = 0;
-    const CUBE_BAG: [u32; 3] = [12, 13, 14]; // Number of red, green, blue in bag
-    'game: for line in reader.lines() {
-        let line = line?;
-        // Format: "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"
-        let (hdr, game_plays) = line
+// Format: "red=12,green=13,blue=14"
+impl FromStr for CubeSet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut cubeset = CubeSet::default();
+        for color_and_num in s.split(',') {
+            let (color, num) = color_and_num
+                .split_once('=')
+                .ok_or_else(|| Error::msg("expected color=number"))?;
+            cubeset.insert(color.trim(), num.trim().parse::<u32>()?)?;
+        }
+        Ok(cubeset)
+    }
+}
+
+impl fmt::Display for CubeSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cubes = self.0.iter().map(|(color, num)| format!("{color}={num}"));
+        write!(f, "{}", cubes.collect::<Vec<_>>().join(","))
+    }
+}
+
+// A color in a draw with more cubes than the bag holds. Draws count from 1.
+#[derive(Debug, Clone, PartialEq)]
+struct Violation {
+    draw: usize,
+    color: String,
+    drawn: u32,
+    in_bag: u32,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "draw {} has {} {} but the bag has {}",
+            self.draw, self.drawn, self.color, self.in_bag
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Game {
+    id: u32,
+    draws: Vec<CubeSet>,
+}
+
+impl FromStr for Game {
+    type Err = Error;
+
+    // Format: "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"
+    fn from_str(s: &str) -> Result<Self> {
+        let (hdr, game_plays) = s
             .split_once(':')
             .ok_or_else(|| Error::msg("invalid game format"))?;
-        for game_play in game_plays.split(';') {
-            let cubeset = parse_cubeset(game_play)?;
-            for (bag, cube) in CUBE_BAG.iter().zip(cubeset.iter()) {
-                if bag < cube {
-                    // this game is impossible
-                    continue 'game;
+        let id = hdr
+            .strip_prefix("Game ")
+            .ok_or_else(|| Error::msg("invalid game header"))?
+            .parse::<u32>()?;
+        let draws = game_plays
+            .split(';')
+            .map(CubeSet::parse_draw)
+            .collect::<Result<_>>()?;
+        Ok(Game { id, draws })
+    }
+}
+
+impl Game {
+    fn is_possible(&self, bag: &CubeSet) -> bool {
+        self.draws.iter().all(|draw| draw.fits_in(bag))
+    }
+
+    // Everything that makes this game impossible with the bag
+    fn violations(&self, bag: &CubeSet) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (i, draw) in self.draws.iter().enumerate() {
+            for (color, &drawn) in &draw.0 {
+                let in_bag = bag.get(color);
+                if drawn > in_bag {
+                    violations.push(Violation {
+                        draw: i + 1,
+                        color: color.clone(),
+                        drawn,
+                        in_bag,
+                    });
                 }
             }
         }
-        let game_id = hdr[5..].parse::<u32>()?;
-        sum += game_id;
+        violations
+    }
+
+    // Smallest factor the bag can be scaled by (rounding cube counts down)
+    // and still have the game be possible, None if it needs a color the bag
+    // doesn't have at all
+    fn min_scale(&self, bag: &CubeSet) -> Option<f64> {
+        let mut scale = 0.0f64;
+        for (color, &needed) in &self.min_cubeset().0 {
+            match bag.get(color) {
+                _ if needed == 0 => (),
+                0 => return None,
+                in_bag => scale = scale.max(needed as f64 / in_bag as f64),
+            }
+        }
+        Some(scale)
+    }
+
+    // Fewest cubes of each color that make every draw possible
+    fn min_cubeset(&self) -> CubeSet {
+        let mut min_cubeset = CubeSet::default();
+        for draw in &self.draws {
+            for (color, &num) in &draw.0 {
+                let min_color = min_cubeset.0.entry(color.clone()).or_insert(0);
+                *min_color = max(*min_color, num);
+            }
+        }
+        min_cubeset
     }
-    Ok(sum)
 }
 
-fn part2(filename: &str) -> Result<u32> {
+fn parse_games(filename: &str) -> Result<Vec<Game>> {
     let file = File::open(filename)?;
     let reader = BufReader::new(file);
-    let mut sum = 0;
-    for line in reader.lines() {
-        let line = line?;
-        // Format: "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"
-        let (_hdr, game_plays) = line
-            .split_once(':')
-            .ok_or_else(|| Error::msg("invalid game format"))?;
-        let mut min_cubeset = [0, 0, 0];
-        for game_play in game_plays.split(';') {
-            let cubeset = parse_cubeset(game_play)?;
-            for (min_color, color) in min_cubeset.iter_mut().zip(cubeset.iter()) {
-                *min_color = max(*min_color, *color);
+    let mut games = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let game = line?
+            .parse::<Game>()
+            .with_context(|| format!("line {}", i + 1))?;
+        games.push(game);
+    }
+    Ok(games)
+}
+
+// Every color drawn in any game
+fn colors(games: &[Game]) -> BTreeSet<&str> {
+    games
+        .iter()
+        .flat_map(|game| &game.draws)
+        .flat_map(|draw| draw.0.keys().map(String::as_str))
+        .collect()
+}
+
+fn part1(games: &[Game], bag: &CubeSet) -> u32 {
+    games
+        .iter()
+        .filter(|game| game.is_possible(bag))
+        .map(|game| game.id)
+        .sum()
+}
+
+// Smallest bag that makes every game possible
+fn min_bag(games: &[Game]) -> CubeSet {
+    let mut bag = CubeSet::default();
+    for game in games {
+        for (color, num) in game.min_cubeset().0 {
+            let in_bag = bag.0.entry(color).or_insert(0);
+            *in_bag = max(*in_bag, num);
+        }
+    }
+    bag
+}
+
+// Number of games possible as the bag is scaled up, as the scale factors at
+// which it changes and the number possible from there on
+fn scaling_curve(games: &[Game], bag: &CubeSet) -> Vec<(f64, usize)> {
+    let mut scales: Vec<f64> = games
+        .iter()
+        .filter_map(|game| game.min_scale(bag))
+        .collect();
+    scales.sort_by(f64::total_cmp);
+    let mut curve: Vec<(f64, usize)> = Vec::new();
+    for (i, scale) in scales.into_iter().enumerate() {
+        match curve.last_mut() {
+            // equal ratios divide to the same float
+            Some(last) if last.0 == scale => last.1 = i + 1,
+            _ => curve.push((scale, i + 1)),
+        }
+    }
+    curve
+}
+
+fn report(games: &[Game], bag: &CubeSet) {
+    println!("bag: {bag}");
+    for game in games {
+        let violations = game.violations(bag);
+        if !violations.is_empty() {
+            println!("game {} is impossible:", game.id);
+            for violation in violations {
+                println!("    {violation}");
             }
         }
-        let power: u32 = min_cubeset.iter().product();
-        sum += power;
     }
-    Ok(sum)
+    println!("minimal bag for all games: {}", min_bag(games));
+
+    println!("{:>8} {:>8}", "scale", "possible");
+    let curve = scaling_curve(games, bag);
+    for &(scale, possible) in &curve {
+        println!("{scale:>8.3} {possible:>8}");
+    }
+    let never = games.len() - curve.last().map_or(0, |&(_, possible)| possible);
+    if never > 0 {
+        println!("{never} games need colors the bag doesn't have");
+    }
+}
+
+// The power of a set is the product over all colors in play, so a game that
+// never shows one of them has power 0
+fn part2(games: &[Game]) -> u32 {
+    let colors = colors(games);
+    games
+        .iter()
+        .map(|game| {
+            let min_cubeset = game.min_cubeset();
+            colors
+                .iter()
+                .map(|color| min_cubeset.get(color))
+                .product::<u32>()
+        })
+        .sum()
 }
 
 fn main() -> Result<()> {
-    let filename = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "inputs/input.txt".to_string());
+    let mut filename = "inputs/input.txt".to_string();
+    let mut bag: CubeSet = CUBE_BAG.parse()?;
+    let mut show_report = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // e.g. --bag red=12,green=13,blue=14,yellow=3
+            "--bag" => {
+                bag = args
+                    .next()
+                    .context("--bag needs color=number,...")?
+                    .parse()?
+            }
+            "--report" => show_report = true,
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
+        }
+    }
+
+    let start = Instant::now();
+    let games = parse_games(&filename)?;
+    let duration = start.elapsed();
+    println!("parsing time: {duration:?}");
+
     let start1 = Instant::now();
-    let sum1 = part1(&filename).expect("failed");
+    let sum1 = part1(&games, &bag);
     let duration1 = start1.elapsed();
     println!("part1: {sum1}, time: {duration1:?}");
 
     let start2 = Instant::now();
-    let sum2 = part2(&filename).expect("failed");
+    let sum2 = part2(&games);
     let duration2 = start2.elapsed();
     println!("part2: {sum2}, time: {duration2:?}");
+
+    if show_report {
+        report(&games, &bag);
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{part1, part2};
+    use super::*;
+
+    fn cubes(s: &str) -> CubeSet {
+        s.parse().unwrap()
+    }
 
     #[test]
     fn test() {
-        let example = "src/inputs/test1.txt";
-        assert_eq!(8, part1(example).unwrap());
-        assert_eq!(2286, part2(example).unwrap());
+        let games = parse_games("src/inputs/test1.txt").unwrap();
+        assert_eq!(8, part1(&games, &cubes(CUBE_BAG)));
+        assert_eq!(2286, part2(&games));
+    }
+
+    #[test]
+    fn test_parse_game() {
+        let game: Game = "Game 12: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"
+            .parse()
+            .unwrap();
+        assert_eq!(12, game.id);
+        assert_eq!(
+            vec![
+                cubes("blue=3,red=4"),
+                cubes("red=1,green=2,blue=6"),
+                cubes("green=2")
+            ],
+            game.draws
+        );
+        assert_eq!(cubes("red=4,green=2,blue=6"), game.min_cubeset());
+        assert!("Game x: 1 red".parse::<Game>().is_err());
+        assert!("Round 1: 1 red".parse::<Game>().is_err());
+        assert!("Game 1: 1 red, 2 red".parse::<Game>().is_err());
+        assert!("Game 1 1 red".parse::<Game>().is_err());
+    }
+
+    #[test]
+    fn test_colors() {
+        let games = [
+            "Game 1: 3 yellow, 1 red; 2 purple".parse().unwrap(),
+            "Game 2: 1 red, 1 purple".parse().unwrap(),
+        ];
+        assert_eq!(BTreeSet::from(["purple", "red", "yellow"]), colors(&games));
+        // game 2 has no yellow, so its power is 0
+        assert_eq!(3 * 2, part2(&games));
+        assert_eq!(3, part1(&games, &cubes("red=1,yellow=3,purple=2")));
+        // a color missing from the bag can't be drawn
+        assert_eq!(2, part1(&games, &cubes("red=1,purple=2")));
+        assert!("red=1,red=2".parse::<CubeSet>().is_err());
+        assert!("red:1".parse::<CubeSet>().is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let games = parse_games("src/inputs/test1.txt").unwrap();
+        let bag = cubes(CUBE_BAG);
+        assert!(games[0].violations(&bag).is_empty());
+        let violation = |draw, color: &str, drawn, in_bag| Violation {
+            draw,
+            color: color.to_string(),
+            drawn,
+            in_bag,
+        };
+        assert_eq!(vec![violation(1, "red", 20, 12)], games[2].violations(&bag));
+        assert_eq!(
+            vec![violation(3, "blue", 15, 14), violation(3, "red", 14, 12)],
+            games[3].violations(&bag)
+        );
+        assert_eq!(
+            "draw 1 has 20 red but the bag has 12",
+            games[2].violations(&bag)[0].to_string()
+        );
+
+        let min_bag = min_bag(&games);
+        assert_eq!("blue=15,green=13,red=20", min_bag.to_string());
+        assert_eq!(1 + 2 + 3 + 4 + 5, part1(&games, &min_bag));
+
+        let curve = scaling_curve(&games, &bag);
+        let possible: Vec<usize> = curve.iter().map(|&(_, possible)| possible).collect();
+        assert_eq!(vec![1, 2, 3, 4, 5], possible);
+        assert_eq!(4.0 / 14.0, curve[0].0);
+        assert_eq!(0.5, curve[2].0);
+        assert_eq!(20.0 / 12.0, curve[4].0);
+        // no game is possible without red
+        assert!(scaling_curve(&games, &cubes("green=13,blue=14")).is_empty());
+    }
+
+    #[test]
+    fn test_solution() {
+        let games = parse_games("src/inputs/input.txt").unwrap();
+        assert_eq!(1734, part1(&games, &cubes(CUBE_BAG)));
+        assert_eq!(70387, part2(&games));
     }
 }
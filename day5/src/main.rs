@@ -1,5 +1,6 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::time::Instant;
@@ -14,8 +15,49 @@ struct Map {
 type Seeds = Vec<usize>;
 type Maps = Vec<Map>;
 
-fn parse_almanac(filename: &str) -> Result<(Seeds, Vec<Maps>)> {
+#[derive(Debug, PartialEq)]
+enum Issue {
+    // Entries (first, second) claim the same source values, the first one listed wins
+    SourceOverlap(usize, usize),
+    // Entries (first, second) map onto the same destination values (not injective)
+    DestOverlap(usize, usize),
+    // Source values not covered by any entry, these map to themselves
+    IdentityGap,
+}
+
+#[derive(Debug, PartialEq)]
+struct Diagnostic {
+    layer: String,
+    issue: Issue,
+    start: usize,
+    end: usize,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (layer, start, end) = (&self.layer, self.start, self.end);
+        match self.issue {
+            Issue::SourceOverlap(first, second) => write!(
+                f,
+                "{layer}: entries {first} and {second} overlap in source range {start}..{end}"
+            ),
+            Issue::DestOverlap(first, second) => write!(
+                f,
+                "{layer}: entries {first} and {second} overlap in destination range {start}..{end}"
+            ),
+            Issue::IdentityGap => {
+                write!(
+                    f,
+                    "{layer}: source range {start}..{end} is unmapped (identity)"
+                )
+            }
+        }
+    }
+}
+
+fn parse_almanac(filename: &str) -> Result<(Seeds, Vec<String>, Vec<Maps>)> {
     let mut seeds: Seeds = Vec::new();
+    let mut names: Vec<String> = Vec::new();
     let mut maps: Vec<Maps> = Vec::new();
 
     let file = File::open(filename)?;
@@ -41,8 +83,9 @@ fn parse_almanac(filename: &str) -> Result<(Seeds, Vec<Maps>)> {
                     .map(|s| s.parse::<usize>().unwrap())
                     .collect();
             }
-            Some((_mapping, _)) => {
-                // println!("{_mapping}");
+            Some((mapping, _)) => {
+                // println!("{mapping}");
+                names.push(mapping.trim_end_matches(" map").to_string());
                 in_mapping = true;
             }
             None => {
@@ -58,7 +101,64 @@ fn parse_almanac(filename: &str) -> Result<(Seeds, Vec<Maps>)> {
     if !map.is_empty() {
         maps.push(map);
     }
-    Ok((seeds, maps))
+    Ok((seeds, names, maps))
+}
+
+// Report overlapping ranges among (entry index, start, len) tuples, as
+// (first index, second index, start, end) for every overlapping pair. Layers
+// only have a few entries so comparing all pairs is fine.
+fn find_overlaps(mut ranges: Vec<(usize, usize, usize)>) -> Vec<(usize, usize, usize, usize)> {
+    let mut overlaps = Vec::new();
+    ranges.sort_unstable();
+    for (k, &(i, start1, len1)) in ranges.iter().enumerate() {
+        for &(j, start2, len2) in &ranges[k + 1..] {
+            let start = start1.max(start2);
+            let end = (start1 + len1).min(start2 + len2);
+            if start < end {
+                overlaps.push((i, j, start, end));
+            }
+        }
+    }
+    overlaps
+}
+
+fn validate_maps(names: &[String], maps: &[Maps]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (layer, map) in names.iter().zip(maps) {
+        let mut report = |issue, start, end| {
+            let layer = layer.clone();
+            diagnostics.push(Diagnostic {
+                layer,
+                issue,
+                start,
+                end,
+            });
+        };
+
+        let srcs = map.iter().enumerate().map(|(i, m)| (i, m.src, m.len));
+        for (first, second, start, end) in find_overlaps(srcs.collect()) {
+            report(Issue::SourceOverlap(first, second), start, end);
+        }
+        let dests = map.iter().enumerate().map(|(i, m)| (i, m.dest, m.len));
+        for (first, second, start, end) in find_overlaps(dests.collect()) {
+            report(Issue::DestOverlap(first, second), start, end);
+        }
+
+        // Gaps between 0 and the end of the highest source range pass through unchanged
+        let mut srcs = map
+            .iter()
+            .map(|m| (m.src, m.src + m.len))
+            .collect::<Vec<_>>();
+        srcs.sort();
+        let mut covered = 0;
+        for (start, end) in srcs {
+            if start > covered {
+                report(Issue::IdentityGap, covered, start);
+            }
+            covered = covered.max(end);
+        }
+    }
+    diagnostics
 }
 
 fn map_seed_to_location(seed: usize, maps: &Vec<Maps>) -> usize {
@@ -129,11 +229,29 @@ fn part2(seeds: &Seeds, maps: &Vec<Maps>) -> usize {
 }
 
 fn main() -> Result<()> {
-    let filename = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "inputs/test1.txt".to_string());
+    let mut filename = "inputs/test1.txt".to_string();
+    let mut validate = false;
+    let mut strict = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--validate" => validate = true,
+            "--strict" => strict = true,
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
+        }
+    }
 
-    let (seeds, maps) = parse_almanac(&filename)?;
+    let (seeds, names, maps) = parse_almanac(&filename)?;
+
+    if validate || strict {
+        let diagnostics = validate_maps(&names, &maps);
+        for d in &diagnostics {
+            println!("{d}");
+        }
+        if strict && !diagnostics.is_empty() {
+            bail!("almanac has {} mapping issues", diagnostics.len());
+        }
+    }
 
     let start1 = Instant::now();
     let sum1 = part1(&seeds, &maps);
@@ -146,3 +264,75 @@ fn main() -> Result<()> {
     println!("part2: {sum2}, time: {duration2:?}");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let filename = "src/inputs/test1.txt";
+        let (seeds, names, maps) = parse_almanac(filename).unwrap();
+        assert_eq!(7, names.len());
+        assert_eq!("seed-to-soil", names[0]);
+        assert_eq!(35, part1(&seeds, &maps));
+        assert_eq!(46, part2(&seeds, &maps));
+    }
+
+    #[test]
+    fn test_validate() {
+        let names = vec!["a-to-b".to_string()];
+        let maps = vec![vec![
+            Map {
+                dest: 100,
+                src: 10,
+                len: 10,
+            },
+            Map {
+                dest: 105,
+                src: 15,
+                len: 10,
+            },
+            Map {
+                dest: 0,
+                src: 30,
+                len: 5,
+            },
+        ]];
+        let diagnostics = validate_maps(&names, &maps);
+        let issues = diagnostics
+            .iter()
+            .map(|d| (&d.issue, d.start, d.end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (&Issue::SourceOverlap(0, 1), 15, 20),
+                (&Issue::DestOverlap(0, 1), 105, 110),
+                (&Issue::IdentityGap, 0, 10),
+                (&Issue::IdentityGap, 25, 30),
+            ],
+            issues
+        );
+
+        // entries 1 and 2 both lie inside entry 0 and also overlap each other
+        let map = |src, len| Map {
+            dest: src,
+            src,
+            len,
+        };
+        let maps = vec![vec![map(0, 100), map(10, 10), map(15, 10)]];
+        let sources = validate_maps(&names, &maps)
+            .into_iter()
+            .filter(|d| matches!(d.issue, Issue::SourceOverlap(..)))
+            .map(|d| (d.issue, d.start, d.end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (Issue::SourceOverlap(0, 1), 10, 20),
+                (Issue::SourceOverlap(0, 2), 15, 25),
+                (Issue::SourceOverlap(1, 2), 15, 20),
+            ],
+            sources
+        );
+    }
+}
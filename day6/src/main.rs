@@ -1,93 +1,125 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Error, Result};
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs;
+use std::str::FromStr;
 use std::time::Instant;
 
-fn parse_race_records(filename: &str) -> Result<(Vec<u32>, Vec<u32>)> {
-    let mut times = Vec::new();
-    let mut records = Vec::new();
-
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        let line = line?;
-        // println!("{line}");
-        match line.split_once(':') {
-            Some(("Time", time_str)) => {
-                times = time_str[1..]
-                    .split_whitespace()
-                    .map(|s| s.parse::<u32>().unwrap())
-                    .collect();
-                // println!("{times:?}");
-            }
-            Some(("Distance", records_str)) => {
-                records = records_str[1..]
-                    .split_whitespace()
-                    .map(|s| s.parse::<u32>().unwrap())
-                    .collect();
-                // println!("{records:?}");
-            }
-            _ => {
-                panic!("Invalid Race Format");
+// The race sheet as written, one column per race. Columns are kept as strings so
+// the kerned interpretation can join the digits exactly as they appear.
+#[derive(Debug)]
+struct RaceSheet {
+    times: Vec<String>,
+    records: Vec<String>,
+}
+
+impl FromStr for RaceSheet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut times = None;
+        let mut records = None;
+        for (i, line) in s.lines().enumerate() {
+            // blank lines, notes and anything else without a label
+            let Some((label, columns)) = line.split_once(':') else {
+                continue;
+            };
+            let column = match label.trim() {
+                "Time" => &mut times,
+                "Distance" => &mut records,
+                _ => continue, // not part of the race sheet
+            };
+            let columns = columns
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            if let Some(col) = columns
+                .iter()
+                .find(|col| !col.bytes().all(|b| b.is_ascii_digit()))
+            {
+                bail!("line {}: invalid number {col}", i + 1);
             }
+            *column = Some(columns);
+        }
+        let times = times.context("missing Time line")?;
+        let records = records.context("missing Distance line")?;
+        if times.len() != records.len() {
+            bail!("{} times but {} distances", times.len(), records.len());
         }
+        Ok(RaceSheet { times, records })
     }
-    Ok((times, records))
 }
 
-fn parse_race_records2(filename: &str) -> Result<(usize, usize)> {
-    let mut time = 0;
-    let mut record = 0;
-
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        let line = line?;
-        // println!("{line}");
-        match line.split_once(':') {
-            Some(("Time", time_str)) => {
-                time = time_str
-                    .chars()
-                    .filter(|c| !c.is_whitespace())
-                    .collect::<String>()
-                    .parse::<usize>()
-                    .unwrap();
-                // println!("{time:?}");
-            }
-            Some(("Distance", records_str)) => {
-                record = records_str
-                    .chars()
-                    .filter(|c| !c.is_whitespace())
-                    .collect::<String>()
-                    .parse::<usize>()
-                    .unwrap();
-                // println!("{record:?}");
-            }
-            _ => {
-                panic!("Invalid Race Format")
+impl RaceSheet {
+    // Each column is a separate race
+    fn races(&self) -> Result<Vec<(u64, u64)>> {
+        self.times
+            .iter()
+            .zip(&self.records)
+            .map(|(time, record)| Ok((time.parse()?, record.parse()?)))
+            .collect()
+    }
+
+    // The spaces are bad kerning, all the columns make up a single race
+    fn kerned_race(&self) -> Result<(u128, u128)> {
+        Ok((self.times.concat().parse()?, self.records.concat().parse()?))
+    }
+}
+
+fn parse_race_sheet(filename: &str) -> Result<RaceSheet> {
+    fs::read_to_string(filename)?.parse()
+}
+
+// Distance covered by holding the button for `hold` ms of a `time` ms race. Saturates so
+// an overflow still compares greater than any record.
+fn distance(hold: u128, time: u128) -> u128 {
+    hold.saturating_mul(time - hold)
+}
+
+// Count the hold times t with t * (time - t) > record. The winning hold times are
+// symmetric around time / 2 and start at the smaller root of t^2 - time*t + record,
+// (time - sqrt(time^2 - 4*record)) / 2. The integer square root may land one off
+// the exact boundary so nudge it until it is the first winning hold time.
+fn ways_to_win<T: Into<u128>>(time: T, record: T) -> u128 {
+    let (time, record) = (time.into(), record.into());
+    let half = time / 2;
+    if distance(half, time) <= record {
+        return 0; // even the best hold time can't beat the record
+    }
+    let mut first = match time.checked_mul(time) {
+        // record < time^2 / 4 here so the discriminant can't underflow or overflow
+        Some(square) => (time - (square - 4 * record).isqrt()) / 2,
+        None => {
+            // time^2 doesn't fit in a u128, binary search the increasing half instead
+            let (mut lo, mut hi) = (0, half);
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if distance(mid, time) > record {
+                    hi = mid;
+                } else {
+                    lo = mid + 1;
+                }
             }
+            lo
         }
+    };
+    while first > 0 && distance(first - 1, time) > record {
+        first -= 1;
+    }
+    while distance(first, time) <= record {
+        first += 1;
     }
-    Ok((time, record))
+    time - 2 * first + 1
 }
 
-fn part1(times: &[u32], records: &[u32]) -> u32 {
-    times
+fn part1(races: &[(u64, u64)]) -> u128 {
+    races
         .iter()
-        .zip(records.iter())
-        .map(|(&time, &record)| {
-            (1..time).rev().find(|t| (time - t) * t > record).unwrap() -
-            (1..time).find(|t| (time - t) * t > record).unwrap()
-        })
+        .map(|&(time, record)| ways_to_win(time, record))
         .product()
 }
 
-fn part2(time: usize, record: usize) -> usize {
-    (1..time).rev().find(|t| (time - t) * t > record).unwrap() -
-        (1..time).find(|t| (time - t) * t > record).unwrap()
+fn part2(race: (u128, u128)) -> u128 {
+    ways_to_win(race.0, race.1)
 }
 
 fn main() -> Result<()> {
@@ -95,15 +127,18 @@ fn main() -> Result<()> {
         .nth(1)
         .unwrap_or_else(|| "inputs/test1.txt".to_string());
 
+    let start = Instant::now();
+    let race_sheet = parse_race_sheet(&filename)?;
+    let duration = start.elapsed();
+    println!("parsing time: {duration:?}");
+
     let start1 = Instant::now();
-    let (times, records) = parse_race_records(&filename)?;
-    let sum1 = part1(&times, &records);
+    let sum1 = part1(&race_sheet.races()?);
     let duration1 = start1.elapsed();
     println!("part1: {sum1}, time: {duration1:?}");
 
     let start2 = Instant::now();
-    let (times, records) = parse_race_records2(&filename)?;
-    let sum2 = part2(times, records);
+    let sum2 = part2(race_sheet.kerned_race()?);
     let duration2 = start2.elapsed();
     println!("part2: {sum2}, time: {duration2:?}");
     Ok(())
@@ -116,18 +151,44 @@ mod tests {
     #[test]
     fn test_sample() {
         let filename = "src/inputs/test1.txt";
-        let (times, records) = parse_race_records(&filename).unwrap();
-        assert_eq!(288, part1(&times, &records));
-        let (time, record) = parse_race_records2(&filename).unwrap();
-        assert_eq!(71503, part2(time, record));
+        let race_sheet = parse_race_sheet(filename).unwrap();
+        assert_eq!(288, part1(&race_sheet.races().unwrap()));
+        assert_eq!(71503, part2(race_sheet.kerned_race().unwrap()));
     }
 
     #[test]
     fn test_solution() {
         let filename = "src/inputs/input.txt";
-        let (times, records) = parse_race_records(&filename).unwrap();
-        assert_eq!(588588, part1(&times, &records));
-        let (time, record) = parse_race_records2(&filename).unwrap();
-        assert_eq!(34655848, part2(time, record));
+        let race_sheet = parse_race_sheet(filename).unwrap();
+        assert_eq!(588588, part1(&race_sheet.races().unwrap()));
+        assert_eq!(34655848, part2(race_sheet.kerned_race().unwrap()));
+    }
+
+    #[test]
+    fn test_race_sheet_layout() {
+        let sheet = "Race sheet: day 6\n\nTime: 7 15    30\n  Distance:9   40 200\n\n"
+            .parse::<RaceSheet>()
+            .unwrap();
+        assert_eq!(vec![(7, 9), (15, 40), (30, 200)], sheet.races().unwrap());
+        assert_eq!((71530, 940200), sheet.kerned_race().unwrap());
+        let sheet = "Time: 7 15\nDistance: 9 40\nnotes here"
+            .parse::<RaceSheet>()
+            .unwrap();
+        assert_eq!(vec![(7, 9), (15, 40)], sheet.races().unwrap());
+        assert!("Time: 7 15\nDistance: 9".parse::<RaceSheet>().is_err());
+        assert!("Time: 7\nDistance: 9x".parse::<RaceSheet>().is_err());
+    }
+
+    #[test]
+    fn test_ways_to_win() {
+        assert_eq!(4, ways_to_win(7u32, 9));
+        assert_eq!(9, ways_to_win(30u32, 200));
+        assert_eq!(0, ways_to_win(30u32, 225));
+        assert_eq!(1, ways_to_win(30u32, 224));
+        assert_eq!(0, ways_to_win(0u32, 0));
+        assert_eq!(u64::MAX as u128 - 1, ways_to_win(u64::MAX, 0));
+        // time^2 overflows a u128 here, the record is the distance of holding 100 ms
+        let (time, hold) = (1u128 << 120, 100);
+        assert_eq!(time - 2 * hold - 1, ways_to_win(time, hold * (time - hold)));
     }
 }
@@ -1,167 +1,414 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
 use std::env;
 use std::fmt;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
+use std::path::Path;
 use std::time::Instant;
 
 struct CardHand {
-    hand: [u8; 5],
+    hand: Vec<u8>,
     bid: u16,
-    score1: u32, // No joker
-    score2: u32, // With joker
 }
 
-enum HandType {
-    HighCard = 1,
-    OnePair = 2,
-    TwoPair = 3,
-    ThreeOfKind = 4,
-    FullHouse = 5,
-    FourOfKind = 6,
-    FiveOfKind = 7,
-}
+// Sizes of the groups of equal cards, largest first, e.g. [3, 2] for a full house.
+// Comparing these lexicographically ranks the hand types for any hand size,
+// including multi-deck hands with more than four of a kind.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HandType(pub Vec<u8>);
 
-impl fmt::Debug for CardHand {
-    // Custom Debug formatter so [u8; 5] is printed as a string
+// The usual names for the five card hand types. Any other signature, from
+// hands of other sizes, is spelled out in full, e.g. "3+3" for two triples.
+impl fmt::Display for HandType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}: {:4}, 0x{:06x}, 0x{:06x}",
-            std::str::from_utf8(&self.hand).expect("invalid sequence"),
-            self.bid,
-            self.score1,
-            self.score2
-        )
+        match self.0.as_slice() {
+            [] => write!(f, "no cards"),
+            [1, 1, 1, 1, 1] => write!(f, "high card"),
+            [2, 1, 1, 1] => write!(f, "one pair"),
+            [2, 2, 1] => write!(f, "two pair"),
+            [3, 1, 1] => write!(f, "three of a kind"),
+            [3, 2] => write!(f, "full house"),
+            [4, 1] => write!(f, "four of a kind"),
+            [5] => write!(f, "five of a kind"),
+            [n] => write!(f, "{n} of a kind"),
+            groups => {
+                let groups = groups.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+                write!(f, "{}", groups.join("+"))
+            }
+        }
+    }
+}
+
+// How a hand scores under a set of rules
+#[derive(Debug, PartialEq)]
+pub struct Classification {
+    pub hand_type: HandType,
+    pub substitution: Option<u8>, // card the wild cards stand in for, if any
+    pub tie_break: Vec<u8>,       // card values compared left to right
+}
+
+impl Classification {
+    // Hands sort by type and then card by card on the tie-break values
+    fn key(&self) -> (&HandType, &[u8]) {
+        (&self.hand_type, &self.tie_break)
     }
 }
 
-fn poker_score(hand: &[u8], joker: bool) -> u8 {
-    const JOKER: usize = 0xb;
-    let mut counts = [0u8; 16];
-    let mut max = 0;
-    let mut max_idx = 0;
-    for c in hand {
-        let idx = *c as usize;
-        counts[idx] += 1;
-        if joker && idx != JOKER && counts[idx] > max {
-            max = counts[idx];
-            max_idx = idx;
+// How wild cards are valued when breaking ties between hands of the same type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WildRank {
+    Natural, // keep their position in the card order
+    Lowest,  // weaker than any other card
+    Highest, // stronger than any other card
+}
+
+// Rules of a camel cards variant
+#[derive(Debug)]
+pub struct CardGame {
+    order: Vec<u8>, // card labels from weakest to strongest
+    wild: Vec<u8>,  // cards that stand in for whatever makes the best hand
+    wild_rank: WildRank,
+}
+
+impl CardGame {
+    pub fn new(order: &str, wild: &str, wild_rank: WildRank) -> Result<Self> {
+        // Leave room in a u8 tie-break value for a lowest/highest wild
+        if order.len() > 254 {
+            bail!("at most 254 cards are supported, got {}", order.len());
+        }
+        // Cards are single bytes, each with its own place in the order
+        for (name, cards) in [("card order", order), ("wild cards", wild)] {
+            if let Some(c) = cards.chars().find(|c| !c.is_ascii()) {
+                bail!("{name} {cards} has non-ASCII card {c}");
+            }
+            if let Some((i, c)) = cards.char_indices().find(|&(i, c)| cards[..i].contains(c)) {
+                bail!("{name} {cards} has {c} twice (position {})", i + 1);
+            }
         }
+        if let Some(c) = wild.chars().find(|&c| !order.contains(c)) {
+            bail!("wild card {c} is not in the card order {order}");
+        }
+        Ok(CardGame {
+            order: order.bytes().collect(),
+            wild: wild.bytes().collect(),
+            wild_rank,
+        })
     }
-    let jokers = counts[JOKER];
-    if joker && jokers != 5 {
-        counts[JOKER] = 0;
-        counts[max_idx] += jokers;
+
+    // Part 1 rules, no wild cards
+    pub fn standard() -> Self {
+        CardGame::new("23456789TJQKA", "", WildRank::Natural).unwrap()
     }
 
-    let mut counts = counts.to_vec();
-    counts.retain(|&v| v != 0);
-    counts.sort();
-
-    // Score hands from 7 to 1
-    let score = match counts[..] {
-        [5] => HandType::FiveOfKind,
-        [1, 4] => HandType::FourOfKind,
-        [2, 3] => HandType::FullHouse,
-        [1, 1, 3] => HandType::ThreeOfKind,
-        [1, 2, 2] => HandType::TwoPair,
-        [1, 1, 1, 2] => HandType::OnePair,
-        _ => HandType::HighCard,
-    };
-    score as u8
+    // Part 2 rules, J is a joker that is the weakest card in tie-breaks
+    pub fn jokers() -> Self {
+        CardGame::new("23456789TJQKA", "J", WildRank::Lowest).unwrap()
+    }
+
+    fn is_wild(&self, card: u8) -> bool {
+        self.wild.contains(&card)
+    }
+
+    // Position of the card in the order, used for counting equal cards
+    fn index(&self, card: u8) -> Result<usize> {
+        self.order
+            .iter()
+            .position(|&c| c == card)
+            .with_context(|| format!("invalid card {}", card as char))
+    }
+
+    // Strength of the card when breaking ties
+    fn value(&self, card: u8) -> Result<u8> {
+        let index = self.index(card)? as u8;
+        if !self.is_wild(card) {
+            return Ok(index + 1);
+        }
+        Ok(match self.wild_rank {
+            WildRank::Natural => index + 1,
+            WildRank::Lowest => 0,
+            WildRank::Highest => self.order.len() as u8 + 1,
+        })
+    }
 }
 
-fn score(hand: &[u8], joker: bool) -> u32 {
-    // Convert hand to hex for easier scoring
-    let hand: [u8; 5] = hand
+pub fn classify(hand: &[u8], rules: &CardGame) -> Result<Classification> {
+    let mut counts = vec![0u8; rules.order.len()];
+    let mut wilds = 0;
+    for &c in hand {
+        if rules.is_wild(c) {
+            wilds += 1;
+        } else {
+            counts[rules.index(c)?] += 1;
+        }
+    }
+    // Wild cards are best spent making the largest group even larger, preferring
+    // the stronger card when groups are the same size
+    let substitution = (0..counts.len())
+        .filter(|&i| counts[i] > 0 && wilds > 0)
+        .max_by_key(|&i| (counts[i], i))
+        .map(|i| rules.order[i]);
+
+    counts.retain(|&v| v != 0);
+    counts.sort_by(|a, b| b.cmp(a));
+    match counts.first_mut() {
+        Some(max) => *max += wilds,
+        None if wilds > 0 => counts.push(wilds),
+        None => (),
+    }
+    let tie_break = hand
         .iter()
-        .map(|b| match b {
-            b'T' => 0xa,
-            b'J' => 0xb,
-            b'Q' => 0xc,
-            b'K' => 0xd,
-            b'A' => 0xe,
-            _ => *b - b'0',
-        })
-        .collect::<Vec<u8>>()
-        .try_into()
-        .unwrap();
-    let mut score = poker_score(&hand, joker) as u32;
-    // shift the bytes into a u32 so we can simply do a numeric comparison.
-    score = hand.iter().fold(score, |score, &b| {
-        (score << 4) | if joker && b == 0xb { 0 } else { b as u32 }
-    });
-    score
-}
-
-fn parse_and_score_card_hands(filename: &str) -> Result<Vec<CardHand>> {
+        .map(|&c| rules.value(c))
+        .collect::<Result<_>>()?;
+    Ok(Classification {
+        hand_type: HandType(counts),
+        substitution,
+        tie_break,
+    })
+}
+
+impl fmt::Debug for CardHand {
+    // Custom Debug formatter so the hand is printed as a string
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:4}",
+            std::str::from_utf8(&self.hand).expect("invalid sequence"),
+            self.bid,
+        )
+    }
+}
+
+fn parse_card_hands(filename: &str) -> Result<Vec<CardHand>> {
     let file = File::open(filename)?;
     let reader = BufReader::new(file);
 
-    let card_hands = reader
+    reader
         .lines()
-        .map(|lines| {
-            let lines = lines.unwrap();
-            let (hand, bid) = lines.split_once(' ').unwrap();
-            let hand: [u8; 5] = hand
-                .bytes()
-                .collect::<Vec<u8>>()
-                .try_into()
-                .expect("invalid card hand");
-            let bid = bid.parse().unwrap();
-            let score1 = score(&hand, false);
-            let score2 = score(&hand, true);
-            CardHand {
-                hand,
-                bid,
-                score1,
-                score2,
+        .map(|line| {
+            let line = line?;
+            let (hand, bid) = line.split_once(' ').context("invalid card hand")?;
+            if hand.is_empty() {
+                bail!("empty card hand");
             }
+            let hand = hand.as_bytes().to_vec();
+            let bid = bid.parse()?;
+            Ok(CardHand { hand, bid })
         })
-        .collect();
-    Ok(card_hands)
+        .collect()
+}
+
+// Hands from weakest to strongest along with how they were classified
+fn rank_hands<'a>(
+    card_hands: &'a [CardHand],
+    game: &CardGame,
+) -> Result<Vec<(&'a CardHand, Classification)>> {
+    let mut ranked = card_hands
+        .iter()
+        .map(|card_hand| Ok((card_hand, classify(&card_hand.hand, game)?)))
+        .collect::<Result<Vec<_>>>()?;
+    ranked.sort_by(|(_, a), (_, b)| a.key().cmp(&b.key()));
+    Ok(ranked)
 }
 
-fn get_winnings(sorted_card_hands: &[CardHand]) -> usize {
-    sorted_card_hands
+fn get_winnings(ranked: &[(&CardHand, Classification)]) -> usize {
+    ranked
         .iter()
         .enumerate()
-        .map(|(i, hand)| (i + 1) * hand.bid as usize)
+        .map(|(i, (hand, _))| (i + 1) * hand.bid as usize)
         .sum()
 }
 
-fn part1(card_hands: &mut [CardHand]) -> usize {
-    card_hands.sort_by(|a, b| a.score1.cmp(&b.score1));
-    // println!("{card_hands:#?}");
-    get_winnings(card_hands)
+fn explain(ranked: &[(&CardHand, Classification)]) {
+    println!(
+        "{:>5}  {:<8} {:<16} {:>4} {:>5} {:>10}  tie-break",
+        "rank", "hand", "type", "wild", "bid", "winnings"
+    );
+    for (i, (card_hand, class)) in ranked.iter().enumerate() {
+        let hand = String::from_utf8_lossy(&card_hand.hand);
+        let wild = class.substitution.map_or('-', |c| c as char);
+        let winnings = (i + 1) * card_hand.bid as usize;
+        println!(
+            "{:>5}  {:<8} {:<16} {:>4} {:>5} {:>10}  {:?}",
+            i + 1,
+            hand,
+            class.hand_type.to_string(),
+            wild,
+            card_hand.bid,
+            winnings,
+            class.tie_break
+        );
+    }
+}
+
+// Where a hand finished without (part 1) and with (part 2) jokers
+#[derive(Debug, Serialize)]
+struct Standing {
+    hand: String,
+    bid: u16,
+    rank: usize,
+    hand_type: String,
+    winnings: usize,
+    joker_rank: usize,
+    joker_hand_type: String,
+    joker_winnings: usize,
+    moved: isize, // places gained once jokers are wild
+}
+
+// Rank (1 = weakest) of each hand, in the same order as the hands
+fn ranks(classes: &[Classification]) -> Vec<usize> {
+    let mut order = (0..classes.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| classes[a].key().cmp(&classes[b].key()));
+    let mut ranks = vec![0; classes.len()];
+    for (rank, i) in order.into_iter().enumerate() {
+        ranks[i] = rank + 1;
+    }
+    ranks
+}
+
+// Standings ordered by the final (joker) ranking
+fn standings(card_hands: &[CardHand]) -> Result<Vec<Standing>> {
+    let classify_all = |game: &CardGame| {
+        card_hands
+            .iter()
+            .map(|card_hand| classify(&card_hand.hand, game))
+            .collect::<Result<Vec<_>>>()
+    };
+    let classes = classify_all(&CardGame::standard())?;
+    let joker_classes = classify_all(&CardGame::jokers())?;
+    let (ranks, joker_ranks) = (ranks(&classes), ranks(&joker_classes));
+
+    let mut standings = card_hands
+        .iter()
+        .enumerate()
+        .map(|(i, card_hand)| {
+            let bid = card_hand.bid;
+            Standing {
+                hand: String::from_utf8_lossy(&card_hand.hand).to_string(),
+                bid,
+                rank: ranks[i],
+                hand_type: classes[i].hand_type.to_string(),
+                winnings: ranks[i] * bid as usize,
+                joker_rank: joker_ranks[i],
+                joker_hand_type: joker_classes[i].hand_type.to_string(),
+                joker_winnings: joker_ranks[i] * bid as usize,
+                moved: joker_ranks[i] as isize - ranks[i] as isize,
+            }
+        })
+        .collect::<Vec<_>>();
+    standings.sort_by_key(|standing| standing.joker_rank);
+    Ok(standings)
+}
+
+fn standings_csv(standings: &[Standing]) -> String {
+    let mut csv = String::from(
+        "hand,bid,rank,hand_type,winnings,joker_rank,joker_hand_type,joker_winnings,moved\n",
+    );
+    for s in standings {
+        csv += &format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            s.hand,
+            s.bid,
+            s.rank,
+            s.hand_type,
+            s.winnings,
+            s.joker_rank,
+            s.joker_hand_type,
+            s.joker_winnings,
+            s.moved
+        );
+    }
+    csv
+}
+
+// Write the standings as CSV or JSON depending on the file extension
+fn export_standings(standings: &[Standing], filename: &str) -> Result<()> {
+    let contents = match Path::new(filename).extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => standings_csv(standings),
+        Some("json") => serde_json::to_string_pretty(standings)?,
+        _ => bail!("{filename}: standings can be exported as .csv or .json"),
+    };
+    fs::write(filename, contents)?;
+    Ok(())
 }
 
-fn part2(card_hands: &mut [CardHand]) -> usize {
-    card_hands.sort_by(|a, b| a.score2.cmp(&b.score2));
-    // println!("{card_hands:#?}");
-    get_winnings(card_hands)
+fn play(card_hands: &[CardHand], game: &CardGame) -> Result<usize> {
+    Ok(get_winnings(&rank_hands(card_hands, game)?))
+}
+
+fn part1(card_hands: &[CardHand]) -> Result<usize> {
+    play(card_hands, &CardGame::standard())
+}
+
+fn part2(card_hands: &[CardHand]) -> Result<usize> {
+    play(card_hands, &CardGame::jokers())
 }
 
 fn main() -> Result<()> {
-    let filename = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "inputs/test1.txt".to_string());
+    let mut filename = "inputs/test1.txt".to_string();
+    let mut order = None;
+    let mut wild = String::new();
+    let mut wild_rank = WildRank::Natural;
+    let mut explain_hands = false;
+    let mut export = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--order" => order = Some(args.next().context("--order needs the cards")?),
+            "--wild" => wild = args.next().context("--wild needs the cards")?,
+            "--wild-rank" => {
+                wild_rank = match args.next().as_deref() {
+                    Some("natural") => WildRank::Natural,
+                    Some("lowest") => WildRank::Lowest,
+                    Some("highest") => WildRank::Highest,
+                    _ => bail!("--wild-rank needs one of natural, lowest, highest"),
+                }
+            }
+            "--explain" => explain_hands = true,
+            "--export" => export = Some(args.next().context("--export needs a filename")?),
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
+        }
+    }
 
     let start = Instant::now();
-    let mut card_hands = parse_and_score_card_hands(&filename)?;
+    let card_hands = parse_card_hands(&filename)?;
     let duration = start.elapsed();
     println!("parsing time: {duration:?}");
 
     let start1 = Instant::now();
-    let sum1 = part1(&mut card_hands);
+    let sum1 = part1(&card_hands)?;
     let duration1 = start1.elapsed();
     println!("part1: {sum1}, time: {duration1:?}");
+    if explain_hands {
+        explain(&rank_hands(&card_hands, &CardGame::standard())?);
+    }
 
     let start2 = Instant::now();
-    let sum2 = part2(&mut card_hands);
+    let sum2 = part2(&card_hands)?;
     let duration2 = start2.elapsed();
     println!("part2: {sum2}, time: {duration2:?}");
+    if explain_hands {
+        explain(&rank_hands(&card_hands, &CardGame::jokers())?);
+    }
+
+    if let Some(export) = export {
+        export_standings(&standings(&card_hands)?, &export)?;
+        println!("standings written to {export}");
+    }
+
+    // House rules, e.g. --wild 2 --wild-rank highest
+    if order.is_some() || !wild.is_empty() {
+        let order = order.unwrap_or_else(|| "23456789TJQKA".to_string());
+        let game = CardGame::new(&order, &wild, wild_rank)?;
+        let start = Instant::now();
+        let sum = play(&card_hands, &game)?;
+        let duration = start.elapsed();
+        println!("custom: {sum}, time: {duration:?}");
+        if explain_hands {
+            explain(&rank_hands(&card_hands, &game)?);
+        }
+    }
     Ok(())
 }
 
@@ -173,17 +420,109 @@ mod tests {
     fn test_sample() {
         let filename = "src/inputs/test1.txt";
 
-        let mut card_hands = parse_and_score_card_hands(&filename).unwrap();
-        assert_eq!(6440, part1(&mut card_hands));
-        assert_eq!(5905, part2(&mut card_hands));
+        let card_hands = parse_card_hands(filename).unwrap();
+        assert_eq!(6440, part1(&card_hands).unwrap());
+        assert_eq!(5905, part2(&card_hands).unwrap());
     }
 
     #[test]
     fn test_solution() {
         let filename = "src/inputs/input.txt";
 
-        let mut card_hands = parse_and_score_card_hands(&filename).unwrap();
-        assert_eq!(253313241, part1(&mut card_hands));
-        assert_eq!(253362743, part2(&mut card_hands));
+        let card_hands = parse_card_hands(filename).unwrap();
+        assert_eq!(253313241, part1(&card_hands).unwrap());
+        assert_eq!(253362743, part2(&card_hands).unwrap());
+    }
+
+    #[test]
+    fn test_standings() {
+        let card_hands = parse_card_hands("src/inputs/test1.txt").unwrap();
+        let standings = standings(&card_hands).unwrap();
+        let moves = standings
+            .iter()
+            .map(|s| (s.hand.as_str(), s.rank, s.joker_rank, s.moved))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("32T3K", 1, 1, 0),
+                ("KK677", 3, 2, -1),
+                ("T55J5", 4, 3, -1),
+                ("QQQJA", 5, 4, -1),
+                ("KTJJT", 2, 5, 3),
+            ],
+            moves
+        );
+        assert_eq!(6440, standings.iter().map(|s| s.winnings).sum::<usize>());
+        assert_eq!(
+            5905,
+            standings.iter().map(|s| s.joker_winnings).sum::<usize>()
+        );
+        let csv = standings_csv(&standings);
+        assert_eq!(
+            Some("KTJJT,220,2,two pair,440,5,four of a kind,1100,3"),
+            csv.lines().last()
+        );
+    }
+
+    #[test]
+    fn test_house_rules() {
+        // Twos are wild and outrank aces in tie-breaks
+        let game = CardGame::new("23456789TJQKA", "2", WildRank::Highest).unwrap();
+        let key = |hand: &[u8]| classify(hand, &game).unwrap();
+        assert!(key(b"2KKKK").key() > key(b"AKKKK").key());
+        assert!(key(b"22345").key() > key(b"AAK34").key());
+        assert!(key(b"2222A").key() > key(b"AAAA2").key());
+        assert!(classify(b"1KKKK", &game).is_err());
+        assert!(CardGame::new("AKQ", "J", WildRank::Lowest).is_err());
+        assert!(CardGame::new("23A4A", "", WildRank::Natural).is_err());
+        assert!(CardGame::new("23A4", "AA", WildRank::Natural).is_err());
+        assert!(CardGame::new("23é4", "", WildRank::Natural).is_err());
+        assert!(CardGame::new("234", "é", WildRank::Natural).is_err());
+    }
+
+    #[test]
+    fn test_hand_sizes() {
+        let game = CardGame::jokers();
+        let hand_type = |hand: &[u8]| classify(hand, &game).unwrap().hand_type;
+        assert_eq!(HandType(vec![3, 3]), hand_type(b"KKKQQQ"));
+        assert_eq!("3+2+1", hand_type(b"KKJQQ2").to_string());
+        assert_eq!("7 of a kind", hand_type(b"AAAAAJJ").to_string());
+        assert_eq!("1+1+1+1+1+1+1", hand_type(b"2345678").to_string());
+        // only exact five card signatures get the usual names
+        let label = |groups: &[u8]| HandType(groups.to_vec()).to_string();
+        assert_eq!("two pair", label(&[2, 2, 1]));
+        assert_eq!("2+2+2", label(&[2, 2, 2]));
+        assert_eq!("2+2+1+1", label(&[2, 2, 1, 1]));
+        assert_eq!("3+3", label(&[3, 3]));
+        assert_eq!("5+2", label(&[5, 2]));
+        assert_eq!("full house", label(&[3, 2]));
+        // Multi-deck hands: six of a kind beats five of a kind plus a pair
+        assert!(hand_type(b"AAAAAAK") > hand_type(b"AAAAAKK"));
+        assert!(hand_type(b"AAAAAKK") > hand_type(b"AAAAAKQ"));
+        let class = |hand: &[u8]| classify(hand, &game).unwrap();
+        assert!(class(b"2222222").key() > class(b"J222222").key());
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            Classification {
+                hand_type: HandType(vec![3, 2]),
+                substitution: Some(b'T'),
+                tie_break: vec![9, 4, 4, 0, 9],
+            },
+            classify(b"T55JT", &CardGame::jokers()).unwrap()
+        );
+        assert_eq!(
+            None,
+            classify(b"JJJJJ", &CardGame::jokers())
+                .unwrap()
+                .substitution
+        );
+        let class = classify(b"T55JT", &CardGame::standard()).unwrap();
+        assert_eq!(
+            (None, "two pair"),
+            (class.substitution, class.hand_type.to_string().as_str())
+        );
     }
 }
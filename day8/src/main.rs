@@ -1,58 +1,203 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use std::collections::HashMap;
 use std::env;
-use std::fs::File;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 use std::time::Instant;
 
-type Node = String;
-type Route = String;
-type Map = HashMap<Node,(Node,Node)>;
+type NodeId = u32;
+type Route = Vec<usize>; // 0 = left, 1 = right
 
-const START: &'static str = "AAA";
-const TERMINAL: &'static str = "ZZZ";
+const START: &str = "AAA";
+const TERMINAL: &str = "ZZZ";
+const UNDEFINED: NodeId = NodeId::MAX;
 
-fn parse_map(filename: &str) -> Result<(Route,Map)> {
+// Selects nodes by label. Parsed from "AAA" (exact), "A*" (prefix), "*Z" (suffix)
+// or "AAA,BBB" (set of labels).
+#[derive(Debug, Clone, PartialEq)]
+enum NodeMatcher {
+    Exact(String),
+    Prefix(String),
+    Suffix(String),
+    Set(Vec<String>),
+}
+
+impl FromStr for NodeMatcher {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            bail!("empty node pattern");
+        }
+        Ok(if s.contains(',') {
+            NodeMatcher::Set(s.split(',').map(|n| n.trim().to_string()).collect())
+        } else if let Some(suffix) = s.strip_prefix('*') {
+            NodeMatcher::Suffix(suffix.to_string())
+        } else if let Some(prefix) = s.strip_suffix('*') {
+            NodeMatcher::Prefix(prefix.to_string())
+        } else {
+            NodeMatcher::Exact(s.to_string())
+        })
+    }
+}
+
+impl fmt::Display for NodeMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeMatcher::Exact(name) => write!(f, "{name}"),
+            NodeMatcher::Prefix(prefix) => write!(f, "{prefix}*"),
+            NodeMatcher::Suffix(suffix) => write!(f, "*{suffix}"),
+            NodeMatcher::Set(names) => write!(f, "{}", names.join(",")),
+        }
+    }
+}
+
+impl NodeMatcher {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            NodeMatcher::Exact(exact) => name == exact,
+            NodeMatcher::Prefix(prefix) => name.starts_with(prefix.as_str()),
+            NodeMatcher::Suffix(suffix) => name.ends_with(suffix.as_str()),
+            NodeMatcher::Set(names) => names.iter().any(|n| n == name),
+        }
+    }
+
+    // Matching nodes in id order
+    fn nodes(&self, network: &Network) -> Vec<NodeId> {
+        (0..network.len() as NodeId)
+            .filter(|&node| self.matches(network.name(node)))
+            .collect()
+    }
+
+    // Lookup table of whether each node id matches
+    fn mask(&self, network: &Network) -> Vec<bool> {
+        (0..network.len() as NodeId)
+            .map(|node| self.matches(network.name(node)))
+            .collect()
+    }
+}
+
+// The desert network with node labels interned into dense ids so walking it is
+// just array indexing. The labels are kept for display.
+#[derive(Debug, Default)]
+struct Network {
+    names: Vec<String>,
+    ids: HashMap<String, NodeId>,
+    next: Vec<NodeId>, // left and right of node n are at 2n and 2n + 1
+}
+
+impl Network {
+    fn intern(&mut self, name: &str) -> NodeId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len() as NodeId;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.next.extend([UNDEFINED, UNDEFINED]);
+        id
+    }
+
+    fn id(&self, name: &str) -> Option<NodeId> {
+        self.ids.get(name).copied()
+    }
+
+    fn name(&self, node: NodeId) -> &str {
+        &self.names[node as usize]
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn step(&self, node: NodeId, dir: usize) -> NodeId {
+        self.next[2 * node as usize + dir]
+    }
+}
+
+fn parse_map(filename: &str) -> Result<(Route, Network)> {
     let file = File::open(filename)?;
     let reader = BufReader::new(file);
 
     let mut route = Route::new();
-    let mut map = HashMap::new();
+    let mut network = Network::default();
 
     for line in reader.lines() {
         let line = line?;
         // println!("{line}");
-	if line.is_empty() {
-	    continue;
-	}
-	if route.is_empty() {
-	    route = line;
-	    continue;
-	}
-	let (node, left_right) = line.split_once('=').unwrap();
-	let node = node.trim().to_owned();
-	let (left, right) = left_right.split_once(",").unwrap();
-	let left = left[2..].to_owned();
- 	let right = right[1..right.len()-1].to_owned();
-	// println!("{node}: ({left}, {right})");
-	map.insert(node, (left, right));
-    }
-    Ok((route, map))
-}
-
-fn part1(route: &str, map: &Map) -> usize {
-    let mut node = START;
-    for (i, r) in route.chars().cycle().enumerate() {
-	let (left, right) = &map[node];
-	node = if r == 'L' { left } else { right };
-	if node == TERMINAL {
-	    return i + 1;
-	}
-    }
-    0
-}
-
-fn gcd(a: usize, b: usize) -> usize {
+        if line.is_empty() {
+            continue;
+        }
+        if route.is_empty() {
+            route = line
+                .chars()
+                .map(|c| match c {
+                    'L' => Ok(0),
+                    'R' => Ok(1),
+                    _ => bail!("invalid direction {c} in route"),
+                })
+                .collect::<Result<_>>()?;
+            continue;
+        }
+        // Format: "AAA = (BBB, CCC)"
+        let (node, left_right) = line.split_once('=').context("invalid node")?;
+        let (left, right) = left_right
+            .trim()
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(|s| s.split_once(','))
+            .context("invalid node")?;
+        // println!("{node}: ({left}, {right})");
+        let node = network.intern(node.trim()) as usize;
+        if network.next[2 * node] != UNDEFINED {
+            bail!("node {} is defined more than once", network.names[node]);
+        }
+        let left = network.intern(left.trim());
+        let right = network.intern(right.trim());
+        network.next[2 * node] = left;
+        network.next[2 * node + 1] = right;
+    }
+    if route.is_empty() {
+        bail!("missing route");
+    }
+    if let Some(node) = (0..network.len()).find(|&node| network.next[2 * node] == UNDEFINED) {
+        bail!("node {} is referenced but not defined", network.names[node]);
+    }
+    Ok((route, network))
+}
+
+// Steps from start until the first terminal node, or None if the walk loops
+// without reaching one
+fn walk(route: &[usize], network: &Network, start: NodeId, is_terminal: &[bool]) -> Option<usize> {
+    let mut node = start;
+    // Once a (node, route position) state repeats the walk is stuck in a loop
+    let mut seen = vec![false; network.len() * route.len()];
+    for (i, &dir) in route.iter().cycle().enumerate() {
+        let state = node as usize * route.len() + i % route.len();
+        if seen[state] {
+            break;
+        }
+        seen[state] = true;
+        node = network.step(node, dir);
+        if is_terminal[node as usize] {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+fn part1(route: &[usize], network: &Network) -> Result<usize> {
+    let start = network
+        .id(START)
+        .with_context(|| format!("start node {START} is not defined"))?;
+    let is_terminal = NodeMatcher::Exact(TERMINAL.to_string()).mask(network);
+    walk(route, network, start, &is_terminal)
+        .with_context(|| format!("terminal {TERMINAL} unreachable from {START}"))
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
     let mut a = a;
     let mut b = b;
 
@@ -64,49 +209,320 @@ fn gcd(a: usize, b: usize) -> usize {
     a
 }
 
-fn part2(route: &str, map: &Map) -> usize {
-    let mut nodes = map.keys().filter(|node| node.ends_with('A')).collect::<Vec<_>>();
-    let mut steps_to_terminal = Vec::new();
-
-    // collect how long it takes reach the terminal for each node
-    for (i, r) in route.chars().cycle().enumerate() {
-	nodes = nodes.iter().map(|&node| {
-	    let (left, right) = &map[node];
-	    if r == 'L' { left } else { right }	    
-	}).collect();
-        if let Some(pos) = nodes.iter().position(|&node| node.ends_with('Z')) {
-            steps_to_terminal.push(i + 1);
-            nodes.remove(pos);
-            if nodes.is_empty() {
-                break;
+// Solve x = a1 (mod m1), x = a2 (mod m2) for moduli that need not be coprime.
+// Returns (x, lcm(m1, m2)) or None if the congruences contradict each other.
+fn crt(a1: u128, m1: u128, a2: u128, m2: u128) -> Option<(u128, u128)> {
+    let g = gcd(m1, m2);
+    let diff = (a2 as i128 - a1 as i128).rem_euclid(m2 as i128) as u128;
+    if !diff.is_multiple_of(g) {
+        return None;
+    }
+    let (m1g, m2g) = (m1 / g, m2 / g);
+    // m1 * k = a2 - a1 (mod m2), divide through by g and invert m1/g
+    let k = diff / g % m2g * mod_inverse(m1g % m2g, m2g) % m2g;
+    let lcm = m1 * m2g;
+    Some(((a1 + m1 * k) % lcm, lcm))
+}
+
+// Inverse of a modulo m via the extended Euclidean algorithm, a and m coprime
+fn mod_inverse(a: u128, m: u128) -> u128 {
+    let (mut old_r, mut r) = (a as i128, m as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+    }
+    old_s.rem_euclid(m as i128) as u128
+}
+
+// A ghost's walk through (node, route position) states. After `cycle_start` steps
+// it repeats every `cycle_len` steps. `hits` are the steps (>= 1) at which the
+// ghost is on a terminal node, up to one time round the cycle.
+#[derive(Debug)]
+struct GhostCycle {
+    cycle_start: usize,
+    cycle_len: usize,
+    hits: Vec<usize>,
+}
+
+impl GhostCycle {
+    // Steps from here on stand for the cycle in `hits`. Step 0 is never a hit,
+    // so a walk that loops back to its start has the cycle at 1..=cycle_len.
+    fn cycle_from(&self) -> usize {
+        self.cycle_start.max(1)
+    }
+
+    fn is_terminal_at(&self, step: usize) -> bool {
+        let from = self.cycle_from();
+        let step = if step < from {
+            step
+        } else {
+            from + (step - from) % self.cycle_len
+        };
+        self.hits.binary_search(&step).is_ok()
+    }
+
+    // The LCM of the first hits only works when the ghost is on a terminal at
+    // exactly the multiples of its first hit
+    fn lcm_shortcut_valid(&self) -> bool {
+        let Some(&first) = self.hits.first() else {
+            return false;
+        };
+        let end = self.cycle_from() + self.cycle_len;
+        self.cycle_len.is_multiple_of(first)
+            && self.hits.iter().copied().eq((first..end).step_by(first))
+    }
+}
+
+fn ghost_cycle(
+    route: &[usize],
+    network: &Network,
+    start: NodeId,
+    is_terminal: &[bool],
+) -> GhostCycle {
+    // step at which each (node, route position) state was first seen
+    let mut seen = vec![usize::MAX; network.len() * route.len()];
+    let mut hits = Vec::new();
+    let mut node = start;
+    let mut step = 0;
+    loop {
+        let state = node as usize * route.len() + step % route.len();
+        if seen[state] != usize::MAX {
+            // back at step 0, whose hit was skipped
+            if seen[state] == 0 && is_terminal[node as usize] {
+                hits.push(step);
             }
+            return GhostCycle {
+                cycle_start: seen[state],
+                cycle_len: step - seen[state],
+                hits,
+            };
         }
+        seen[state] = step;
+        if step > 0 && is_terminal[node as usize] {
+            hits.push(step);
+        }
+        node = network.step(node, route[step % route.len()]);
+        step += 1;
     }
+}
 
-    // now get the LCM because the paths keep cycling
-    let mut lcm = steps_to_terminal[0];
-    for v in &steps_to_terminal[1..] {
-        lcm = lcm * *v / gcd(lcm, *v);
+// First step at which every ghost is on a terminal node at the same time
+fn synchronize(cycles: &[GhostCycle]) -> Option<u128> {
+    // Before every ghost is cycling just check each step directly
+    let settled = cycles.iter().map(|c| c.cycle_start).max()?.max(1);
+    if let Some(step) = (1..settled).find(|&step| cycles.iter().all(|c| c.is_terminal_at(step))) {
+        return Some(step as u128);
     }
-    lcm
+
+    // From then on each ghost is on a terminal when step = hit (mod cycle_len) for
+    // one of its hits in the cycle. Try every combination of hits across ghosts.
+    let mut candidates = vec![(0u128, 1u128)];
+    for c in cycles {
+        let (start, len) = (c.cycle_start, c.cycle_len as u128);
+        let residues = c
+            .hits
+            .iter()
+            .filter(|&&hit| hit >= start)
+            .map(|&hit| hit as u128 % len);
+        candidates = residues
+            .flat_map(|r| {
+                candidates
+                    .iter()
+                    .filter_map(move |&(a, m)| crt(a, m, r, len))
+            })
+            .collect();
+    }
+    let settled = settled as u128;
+    candidates
+        .into_iter()
+        .map(|(a, m)| {
+            if a >= settled {
+                a
+            } else {
+                a + (settled - a).div_ceil(m) * m
+            }
+        })
+        .min()
+}
+
+fn ghost_cycles(
+    route: &[usize],
+    network: &Network,
+    start: &NodeMatcher,
+    terminal: &NodeMatcher,
+) -> Vec<GhostCycle> {
+    let is_terminal = terminal.mask(network);
+    start
+        .nodes(network)
+        .into_iter()
+        .map(|start| ghost_cycle(route, network, start, &is_terminal))
+        .collect()
+}
+
+// The first step all ghosts are on a terminal, and whether the LCM of each
+// ghost's first hit would have given the same answer
+fn part2(route: &[usize], network: &Network) -> (Option<u128>, bool) {
+    let start = NodeMatcher::Suffix("A".to_string());
+    let terminal = NodeMatcher::Suffix("Z".to_string());
+    let cycles = ghost_cycles(route, network, &start, &terminal);
+    let shortcut = cycles.iter().all(|c| c.lcm_shortcut_valid());
+    (synchronize(&cycles), shortcut)
+}
+
+// Walk from every node matching start to the first node matching terminal, and
+// report when all of them would be on a terminal together
+fn explore(route: &[usize], network: &Network, start: &NodeMatcher, terminal: &NodeMatcher) {
+    let starts = start.nodes(network);
+    if starts.is_empty() {
+        println!("no nodes match {start}");
+        return;
+    }
+    let is_terminal = terminal.mask(network);
+    for &node in &starts {
+        match walk(route, network, node, &is_terminal) {
+            Some(steps) => println!("{} -> {terminal}: {steps}", network.name(node)),
+            None => println!("{} -> {terminal}: unreachable", network.name(node)),
+        }
+    }
+    if starts.len() > 1 {
+        match synchronize(&ghost_cycles(route, network, start, terminal)) {
+            Some(steps) => println!("{start} -> {terminal} together: {steps}"),
+            None => println!("{start} -> {terminal} together: never"),
+        }
+    }
+}
+
+// Graphviz rendering of the network with L/R edge labels, start and terminal
+// nodes highlighted and optionally the cycle each start node ends up in coloured
+fn network_dot(
+    route: &[usize],
+    network: &Network,
+    start: &NodeMatcher,
+    terminal: &NodeMatcher,
+    color_cycles: bool,
+) -> String {
+    const CYCLE_COLORS: [&str; 6] = ["blue", "red", "darkgreen", "orange", "purple", "brown"];
+    // colour of each (node, direction) edge that is part of a ghost's cycle
+    let mut edge_colors = HashMap::new();
+    if color_cycles {
+        let is_terminal = terminal.mask(network);
+        for (i, node) in start.nodes(network).into_iter().enumerate() {
+            let cycle = ghost_cycle(route, network, node, &is_terminal);
+            let mut node = node;
+            for step in 0..cycle.cycle_start + cycle.cycle_len {
+                let dir = route[step % route.len()];
+                if step >= cycle.cycle_start {
+                    edge_colors.insert((node, dir), CYCLE_COLORS[i % CYCLE_COLORS.len()]);
+                }
+                node = network.step(node, dir);
+            }
+        }
+    }
+
+    let mut dot = String::from("digraph network {\n");
+    for node in 0..network.len() as NodeId {
+        let name = network.name(node);
+        if start.matches(name) {
+            dot += &format!("    {name:?} [style=filled, fillcolor=palegreen];\n");
+        } else if terminal.matches(name) {
+            dot += &format!("    {name:?} [style=filled, fillcolor=salmon];\n");
+        }
+    }
+    for node in 0..network.len() as NodeId {
+        let name = network.name(node);
+        let (left, right) = (network.step(node, 0), network.step(node, 1));
+        // A single edge when both directions lead to the same node
+        let edges = if left == right {
+            vec![(left, "L/R", 0)]
+        } else {
+            vec![(left, "L", 0), (right, "R", 1)]
+        };
+        for (next, label, dir) in edges {
+            let next = network.name(next);
+            let color = edge_colors
+                .get(&(node, dir))
+                .or_else(|| edge_colors.get(&(node, 1 - dir)).filter(|_| left == right));
+            match color {
+                Some(color) => {
+                    dot += &format!(
+                        "    {name:?} -> {next:?} [label={label:?}, color={color}, penwidth=2];\n"
+                    )
+                }
+                None => dot += &format!("    {name:?} -> {next:?} [label={label:?}];\n"),
+            }
+        }
+    }
+    dot += "}\n";
+    dot
 }
 
 fn main() -> Result<()> {
-    let filename = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "inputs/test1.txt".to_string());
+    let mut filename = "inputs/test1.txt".to_string();
+    let mut from = None;
+    let mut to = None;
+    let mut dot = None;
+    let mut dot_cycles = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => {
+                from = Some(
+                    args.next()
+                        .context("--from needs a node pattern")?
+                        .parse()?,
+                )
+            }
+            "--to" => to = Some(args.next().context("--to needs a node pattern")?.parse()?),
+            "--dot" => dot = Some(args.next().context("--dot needs a filename")?),
+            "--dot-cycles" => dot_cycles = true,
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
+        }
+    }
 
-    let (route, map) = parse_map(&filename)?;
+    let start = Instant::now();
+    let (route, network) = parse_map(&filename)?;
+    let duration = start.elapsed();
+    println!("parsing time: {duration:?}");
 
     let start1 = Instant::now();
-    let sum1 = part1(&route, &map);
-    let duration1 = start1.elapsed();
-    println!("part1: {sum1}, time: {duration1:?}");
+    // The part 2 samples have no AAA so keep going on to part 2
+    match part1(&route, &network) {
+        Ok(sum1) => {
+            let duration1 = start1.elapsed();
+            println!("part1: {sum1}, time: {duration1:?}");
+        }
+        Err(err) => println!("part1: {err}"),
+    }
 
     let start2 = Instant::now();
-    let sum2 = part2(&route, &map);
-    let duration2 = start2.elapsed();
-    println!("part2: {sum2}, time: {duration2:?}");
+    // A broken network is still worth exploring or exporting, so carry on
+    match part2(&route, &network) {
+        (Some(sum2), shortcut) => {
+            let duration2 = start2.elapsed();
+            println!("part2: {sum2}, time: {duration2:?}");
+            println!("lcm shortcut valid: {shortcut}");
+        }
+        (None, _) => println!("part2: the ghosts never line up"),
+    }
+
+    // Render with e.g. dot -Tsvg network.dot -o network.svg
+    if let Some(dot) = dot {
+        let start = from.clone().unwrap_or(NodeMatcher::Suffix("A".to_string()));
+        let terminal = to.clone().unwrap_or(NodeMatcher::Suffix("Z".to_string()));
+        let graph = network_dot(&route, &network, &start, &terminal, dot_cycles);
+        fs::write(&dot, graph)?;
+        println!("network written to {dot}");
+    }
+
+    // e.g. --from BBB --to '*Q'
+    if from.is_some() || to.is_some() {
+        let from = from.unwrap_or(NodeMatcher::Exact(START.to_string()));
+        let to = to.unwrap_or(NodeMatcher::Exact(TERMINAL.to_string()));
+        explore(&route, &network, &from, &to);
+    }
     Ok(())
 }
 
@@ -117,8 +533,129 @@ mod tests {
     #[test]
     fn test_sample1() {
         let filename = "src/inputs/test1.txt";
-        let (route, map) = parse_map(&filename).unwrap();
-        assert_eq!(288, part1(&route, &map));
-        assert_eq!(71503, part2(&route, &map));
+        let (route, network) = parse_map(filename).unwrap();
+        assert_eq!(2, part1(&route, &network).unwrap());
+        // ZZZ loops onto itself, so the LCM is only right by luck
+        assert_eq!((Some(2), false), part2(&route, &network));
+    }
+
+    #[test]
+    fn test_sample2() {
+        let filename = "src/inputs/test2.txt";
+        let (route, network) = parse_map(filename).unwrap();
+        assert_eq!(6, part1(&route, &network).unwrap());
+    }
+
+    #[test]
+    fn test_sample3() {
+        let filename = "src/inputs/test3.txt";
+        let (route, network) = parse_map(filename).unwrap();
+        assert_eq!((Some(6), true), part2(&route, &network));
+        let err = part1(&route, &network).unwrap_err();
+        assert_eq!("start node AAA is not defined", err.to_string());
+    }
+
+    #[test]
+    fn test_solution() {
+        let filename = "src/inputs/input.txt";
+        let (route, network) = parse_map(filename).unwrap();
+        assert_eq!("GXT", network.name(0));
+        assert_eq!(Some(0), network.id("GXT"));
+        assert_eq!(13207, part1(&route, &network).unwrap());
+        assert_eq!((Some(12324145107121), true), part2(&route, &network));
+    }
+
+    #[test]
+    fn test_offset_cycles() {
+        // 22A hits 22Z after 1 step and then every 3, 33A has two terminals in
+        // its cycle, so the LCM of the first hits (2) is wrong
+        let filename = "src/inputs/test4.txt";
+        let (route, network) = parse_map(filename).unwrap();
+        let start = NodeMatcher::Suffix("A".to_string());
+        let terminal = NodeMatcher::Suffix("Z".to_string());
+        let cycles = ghost_cycles(&route, &network, &start, &terminal);
+        assert_eq!(
+            vec![true, false, false],
+            cycles
+                .iter()
+                .map(|c| c.lcm_shortcut_valid())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!((Some(4), false), part2(&route, &network));
+    }
+
+    #[test]
+    fn test_terminal_start() {
+        // AAZ is a start and a terminal and loops back to itself every 2 steps
+        let (route, network) = parse_map("src/inputs/test7.txt").unwrap();
+        let start = "AAZ,CCA".parse::<NodeMatcher>().unwrap();
+        let terminal = NodeMatcher::Suffix("Z".to_string());
+        let cycles = ghost_cycles(&route, &network, &start, &terminal);
+        assert_eq!(vec![2], cycles[0].hits);
+        assert!(cycles[0].is_terminal_at(4));
+        assert!(!cycles[0].is_terminal_at(3));
+        assert!(cycles[0].lcm_shortcut_valid());
+        assert_eq!(Some(2), synchronize(&cycles));
+    }
+
+    #[test]
+    fn test_invalid_networks() {
+        let (route, network) = parse_map("src/inputs/test5.txt").unwrap();
+        let err = part1(&route, &network).unwrap_err();
+        assert_eq!("terminal ZZZ unreachable from AAA", err.to_string());
+        let err = parse_map("src/inputs/test6.txt").unwrap_err();
+        assert_eq!("node CCC is referenced but not defined", err.to_string());
+    }
+
+    #[test]
+    fn test_node_matcher() {
+        let (route, network) = parse_map("src/inputs/test3.txt").unwrap();
+        let ids = |pattern: &str| {
+            let matcher = pattern.parse::<NodeMatcher>().unwrap();
+            assert_eq!(pattern, matcher.to_string());
+            matcher
+                .nodes(&network)
+                .iter()
+                .map(|&node| network.name(node))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["11A", "11B", "11Z"], ids("11*"));
+        assert_eq!(vec!["11B", "22B"], ids("*B"));
+        assert_eq!(vec!["22C"], ids("22C"));
+        assert_eq!(vec!["11B", "XXX"], ids("XXX,11B"));
+        let is_terminal = "22Z,11B".parse::<NodeMatcher>().unwrap().mask(&network);
+        assert_eq!(Some(1), walk(&route, &network, 0, &is_terminal));
+        assert_eq!(
+            None,
+            walk(&route, &network, network.id("XXX").unwrap(), &is_terminal)
+        );
+    }
+
+    #[test]
+    fn test_network_dot() {
+        let (route, network) = parse_map("src/inputs/test3.txt").unwrap();
+        let start = NodeMatcher::Suffix("A".to_string());
+        let terminal = NodeMatcher::Suffix("Z".to_string());
+        let dot = network_dot(&route, &network, &start, &terminal, false);
+        assert!(dot.starts_with("digraph network {\n"));
+        assert!(dot.contains("    \"11A\" [style=filled, fillcolor=palegreen];\n"));
+        assert!(dot.contains("    \"22Z\" [style=filled, fillcolor=salmon];\n"));
+        assert!(dot.contains("    \"11A\" -> \"11B\" [label=\"L\"];\n"));
+        assert!(dot.contains("    \"22B\" -> \"22C\" [label=\"L/R\"];\n"));
+        assert!(dot.ends_with("}\n"));
+
+        // 11A leads into 11B -> 11Z -> 11B, taking 11B's right and 11Z's left edge
+        let dot = network_dot(&route, &network, &start, &terminal, true);
+        assert!(dot.contains("\"11A\" -> \"11B\" [label=\"L\"];"));
+        assert!(dot.contains("\"11B\" -> \"11Z\" [label=\"R\", color=blue, penwidth=2];"));
+        assert!(dot.contains("\"11Z\" -> \"11B\" [label=\"L\", color=blue, penwidth=2];"));
+        assert!(dot.contains("\"22Z\" -> \"22B\" [label=\"L/R\", color=red, penwidth=2];"));
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(Some((4, 6)), crt(0, 2, 1, 3));
+        assert_eq!(Some((7, 12)), crt(3, 4, 1, 6));
+        assert_eq!(None, crt(0, 4, 1, 6));
     }
 }
@@ -0,0 +1,212 @@
+// Finite differences of a sequence sampled at consecutive integers. If the
+// sequence comes from a polynomial of degree d, the (d + 1)th differences are
+// all zero and Newton's interpolation formulas extend it exactly in either
+// direction.
+
+use anyhow::{bail, Result};
+use num_bigint::BigInt;
+use std::fmt;
+
+// Reported when a value doesn't fit the numeric type, so the caller can retry
+// with a wider one
+#[derive(Debug)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "arithmetic overflow")
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+// Numeric type the table is computed in. Every intermediate value is an
+// integer (the binomial divisions are exact) so BigInt is as precise as a
+// rational type would be.
+pub trait Number: Clone + Into<BigInt> {
+    fn from_i64(v: i64) -> Self;
+    fn try_from_i128(v: i128) -> Result<Self, Overflow>;
+    fn is_zero(&self) -> bool;
+    fn try_add(&self, other: &Self) -> Result<Self, Overflow>;
+    fn try_sub(&self, other: &Self) -> Result<Self, Overflow>;
+    fn try_mul(&self, other: &Self) -> Result<Self, Overflow>;
+    fn try_div(&self, other: &Self) -> Result<Self, Overflow>;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                fn from_i64(v: i64) -> Self {
+                    v as $t
+                }
+                fn try_from_i128(v: i128) -> Result<Self, Overflow> {
+                    <$t>::try_from(v).map_err(|_| Overflow)
+                }
+                fn is_zero(&self) -> bool {
+                    *self == 0
+                }
+                fn try_add(&self, other: &Self) -> Result<Self, Overflow> {
+                    self.checked_add(*other).ok_or(Overflow)
+                }
+                fn try_sub(&self, other: &Self) -> Result<Self, Overflow> {
+                    self.checked_sub(*other).ok_or(Overflow)
+                }
+                fn try_mul(&self, other: &Self) -> Result<Self, Overflow> {
+                    self.checked_mul(*other).ok_or(Overflow)
+                }
+                fn try_div(&self, other: &Self) -> Result<Self, Overflow> {
+                    self.checked_div(*other).ok_or(Overflow)
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(i64, i128);
+
+impl Number for BigInt {
+    fn from_i64(v: i64) -> Self {
+        BigInt::from(v)
+    }
+    fn try_from_i128(v: i128) -> Result<Self, Overflow> {
+        Ok(BigInt::from(v))
+    }
+    fn is_zero(&self) -> bool {
+        *self == BigInt::from(0)
+    }
+    fn try_add(&self, other: &Self) -> Result<Self, Overflow> {
+        Ok(self + other)
+    }
+    fn try_sub(&self, other: &Self) -> Result<Self, Overflow> {
+        Ok(self - other)
+    }
+    fn try_mul(&self, other: &Self) -> Result<Self, Overflow> {
+        Ok(self * other)
+    }
+    fn try_div(&self, other: &Self) -> Result<Self, Overflow> {
+        Ok(self / other)
+    }
+}
+
+#[derive(Debug)]
+pub struct DifferenceTable<T> {
+    // rows[0] is the sequence, rows[j] the jth differences. The table stops at
+    // the first all zero row, which is not stored.
+    rows: Vec<Vec<T>>,
+}
+
+impl<T: Number> DifferenceTable<T> {
+    // Fails if the differences run out before reaching an all zero row. The
+    // values then don't pin down a polynomial, e.g. 1 2 4 8 16.
+    pub fn new(values: &[T]) -> Result<Self> {
+        if values.is_empty() {
+            bail!("empty history");
+        }
+        let mut rows = Vec::new();
+        let mut row = values.to_vec();
+        while row.iter().any(|v| !v.is_zero()) {
+            if row.len() == 1 {
+                bail!(
+                    "{} values are not enough to confirm a polynomial of degree {}",
+                    values.len(),
+                    values.len() - 1
+                );
+            }
+            let next = row
+                .windows(2)
+                .map(|w| w[1].try_sub(&w[0]))
+                .collect::<Result<_, _>>()?;
+            rows.push(row);
+            row = next;
+        }
+        Ok(DifferenceTable { rows })
+    }
+
+    // Value k steps after the last value, or -k steps before the first when k
+    // is negative. Uses the Newton backward formula from the end of the table
+    //   f(n - 1 + k) = sum_j C(k + j - 1, j) * nabla^j f(n - 1)
+    // and the forward formula from the start for negative k, where
+    //   f(k) = sum_j C(k, j) * delta^j f(0) = sum_j (-1)^j C(-k + j - 1, j) * delta^j f(0)
+    pub fn extrapolate(&self, k: i64) -> Result<T, Overflow> {
+        // i128 holds |i64::MIN| and then some, so only T can overflow
+        let steps = i128::from(k.unsigned_abs());
+        let mut sum = T::from_i64(0);
+        let mut binomial = T::from_i64(1); // C(steps + j - 1, j)
+        for (j, row) in self.rows.iter().enumerate() {
+            if j > 0 {
+                let top = T::try_from_i128(steps + j as i128 - 1)?;
+                binomial = binomial.try_mul(&top)?.try_div(&T::from_i64(j as i64))?;
+            }
+            if k >= 0 {
+                sum = sum.try_add(&binomial.try_mul(row.last().unwrap())?)?;
+            } else if j % 2 == 0 {
+                sum = sum.try_add(&binomial.try_mul(row.first().unwrap())?)?;
+            } else {
+                sum = sum.try_sub(&binomial.try_mul(row.first().unwrap())?)?;
+            }
+        }
+        Ok(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extrapolate() {
+        // n^2 + 1 for n = 0..5
+        let table = DifferenceTable::<i64>::new(&[1, 2, 5, 10, 17]).unwrap();
+        assert_eq!(17, table.extrapolate(0).unwrap());
+        assert_eq!(26, table.extrapolate(1).unwrap());
+        assert_eq!(101, table.extrapolate(6).unwrap());
+        assert_eq!(2, table.extrapolate(-1).unwrap());
+        assert_eq!(10, table.extrapolate(-3).unwrap());
+        let zeros = DifferenceTable::<i64>::new(&[0, 0, 0]).unwrap();
+        assert_eq!(0, zeros.extrapolate(5).unwrap());
+    }
+
+    #[test]
+    fn test_not_polynomial() {
+        assert!(DifferenceTable::<i64>::new(&[]).is_err());
+        assert!(DifferenceTable::<i64>::new(&[1, 2, 4, 8, 16]).is_err());
+        // a single value is not enough to tell it is constant
+        assert!(DifferenceTable::<i64>::new(&[7]).is_err());
+        assert!(DifferenceTable::<i64>::new(&[7, 7]).is_ok());
+    }
+
+    #[test]
+    fn test_overflow() {
+        // n^3 at n = 10^12 is 10^36, past i64 but within i128
+        let cubes = [0, 1, 8, 27, 64];
+        let k = 1_000_000_000_000 - 4;
+        let table = DifferenceTable::<i64>::new(&cubes).unwrap();
+        assert!(table.extrapolate(k).is_err());
+        let table = DifferenceTable::<i128>::new(&cubes.map(i128::from)).unwrap();
+        assert_eq!(10i128.pow(36), table.extrapolate(k).unwrap());
+        // and n^3 at n = 10^15 only fits a BigInt
+        let table = DifferenceTable::<i128>::new(&cubes.map(i128::from)).unwrap();
+        assert!(table.extrapolate(1000 * k + 3996).is_err());
+        let table = DifferenceTable::<BigInt>::new(&cubes.map(BigInt::from)).unwrap();
+        let expected = BigInt::from(10).pow(45);
+        assert_eq!(expected, table.extrapolate(1000 * k + 3996).unwrap());
+    }
+
+    #[test]
+    fn test_extreme_steps() {
+        let cubes = [0, 1, 8, 27, 64];
+        let table = DifferenceTable::<i64>::new(&cubes).unwrap();
+        assert!(table.extrapolate(i64::MIN).is_err());
+        assert!(table.extrapolate(i64::MAX).is_err());
+        let table = DifferenceTable::<BigInt>::new(&cubes.map(BigInt::from)).unwrap();
+        assert_eq!(
+            BigInt::from(i64::MIN).pow(3),
+            table.extrapolate(i64::MIN).unwrap()
+        );
+        assert_eq!(
+            (BigInt::from(i64::MAX) + BigInt::from(4)).pow(3),
+            table.extrapolate(i64::MAX).unwrap()
+        );
+    }
+}
@@ -1,78 +1,148 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 use std::time::Instant;
 
-fn parse_sensor_readings(filename: &str) -> Result<Vec<Vec<i32>>> {
+mod finite_diff;
+mod polyfit;
+
+use finite_diff::{DifferenceTable, Number, Overflow};
+use num_bigint::BigInt;
+
+fn parse_sensor_readings(filename: &str) -> Result<Vec<Vec<i64>>> {
     let mut sensor_readings = Vec::new();
 
     let file = File::open(filename)?;
     let reader = BufReader::new(file);
 
-    for line in reader.lines() {
+    for (i, line) in reader.lines().enumerate() {
         let line = line?;
-        sensor_readings.push(
-            line.split_whitespace()
-                .map(|s| s.parse::<i32>().unwrap())
-                .collect(),
-        );
+        let reading_history = line
+            .split_whitespace()
+            .map(|s| s.parse::<i64>())
+            .collect::<Result<_, _>>()
+            .with_context(|| format!("line {}: invalid reading", i + 1))?;
+        sensor_readings.push(reading_history);
     }
     Ok(sensor_readings)
 }
 
-fn part1(sensor_readings: &[Vec<i32>]) -> i32 {
-    let mut sum = 0;
-    for reading_history in sensor_readings {
-        let mut diffs = reading_history
-            .windows(2)
-            .map(|w| w[1] - w[0])
-            .collect::<Vec<_>>();
-        let mut diff_sum = 0;
-        while diffs.iter().filter(|&&v| v == 0).count() != diffs.len() {
-            // println!("{diffs:?}");
-            diff_sum += diffs.last().unwrap();
-            diffs = diffs.windows(2).map(|w| w[1] - w[0]).collect();
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Backend {
+    I64,
+    I128,
+    BigInt,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "i64" => Ok(Backend::I64),
+            "i128" => Ok(Backend::I128),
+            "bigint" => Ok(Backend::BigInt),
+            _ => bail!("unknown backend {s}, expected i64, i128 or bigint"),
         }
-        let next = reading_history.last().unwrap() + diff_sum;
-        // println!("diff_sum: {diff_sum}, next_item: {next}");
-        sum += next;
     }
-    sum
 }
 
-fn part2(sensor_readings: &[Vec<i32>]) -> i32 {
-    let mut sum = 0;
-    for reading_history in sensor_readings {
-        let mut diffs = reading_history
-            .windows(2)
-            .map(|w| w[1] - w[0])
+// Sum of each history extrapolated k steps forward (or backward if negative).
+// Histories that aren't exactly polynomial are an error unless a degree is given
+// to fall back to a least squares fit, rounded to the nearest integer.
+fn extrapolate_with<T: Number>(
+    sensor_readings: &[Vec<i64>],
+    k: i64,
+    fit: Option<usize>,
+) -> Result<T> {
+    let mut sum = T::from_i64(0);
+    for (i, reading_history) in sensor_readings.iter().enumerate() {
+        let values = reading_history
+            .iter()
+            .map(|&v| T::from_i64(v))
             .collect::<Vec<_>>();
-        
-        // If a, b, c, d, e are the first values in the differences, the final diff
-        // to subtract from the first element in the sequence works out to
-        // (a - (b - (c - (d - e))). This can be simplifed to a - b + c - d + e,
-        // so essentially flipping the sign and adding first values instead of
-        // storing the first values and computing this later.
-        let mut diff_sum = 0;
-        let mut sign_flip = 1;
-        while diffs.iter().filter(|&&v| v == 0).count() != diffs.len() {
-            // println!("{diffs:?}");
-            diff_sum += sign_flip * diffs.first().unwrap();
-            sign_flip = -sign_flip;
-            diffs = diffs.windows(2).map(|w| w[1] - w[0]).collect();
+        let next = match (DifferenceTable::new(&values), fit) {
+            (Ok(table), _) => table.extrapolate(k)?,
+            (Err(err), _) if err.is::<Overflow>() => return Err(err),
+            (Err(_), Some(degree)) => {
+                let coefficients = polyfit::fit_polynomial(reading_history, degree)
+                    .with_context(|| format!("line {}", i + 1))?;
+                let x = if k >= 0 {
+                    (reading_history.len() as i64 - 1)
+                        .checked_add(k)
+                        .with_context(|| format!("line {}: step {k} is out of range", i + 1))?
+                } else {
+                    k
+                };
+                // The fit is only as precise as an f64, so a wider backend
+                // wouldn't help with a value past the i64 range
+                let fitted = polyfit::evaluate(&coefficients, x as f64).round();
+                if !(fitted.is_finite() && (i64::MIN as f64..i64::MAX as f64).contains(&fitted)) {
+                    bail!(
+                        "line {}: fitted value {fitted} at {x} is out of range",
+                        i + 1
+                    );
+                }
+                T::from_i64(fitted as i64)
+            }
+            (Err(err), None) => return Err(err.context(format!("line {}", i + 1))),
+        };
+        sum = sum.try_add(&next)?;
+    }
+    Ok(sum)
+}
+
+// Runs the given backend, or without one tries i64, i128 and BigInt in turn
+// until the arithmetic no longer overflows. Returns the backend that was used.
+fn extrapolate(
+    sensor_readings: &[Vec<i64>],
+    k: i64,
+    fit: Option<usize>,
+    backend: Option<Backend>,
+) -> Result<(BigInt, Backend)> {
+    let backends = match backend {
+        Some(backend) => vec![backend],
+        None => vec![Backend::I64, Backend::I128, Backend::BigInt],
+    };
+    for backend in backends {
+        let sum = match backend {
+            Backend::I64 => extrapolate_with::<i64>(sensor_readings, k, fit).map(BigInt::from),
+            Backend::I128 => extrapolate_with::<i128>(sensor_readings, k, fit).map(BigInt::from),
+            Backend::BigInt => extrapolate_with::<BigInt>(sensor_readings, k, fit),
+        };
+        match sum {
+            Err(err) if err.is::<Overflow>() => continue,
+            sum => return sum.map(|sum| (sum, backend)),
         }
-        let prev = reading_history.first().unwrap() - diff_sum;
-        // println!("diff_sum: {diff_sum:?}, prev: {prev}");
-        sum += prev;
     }
-    sum
+    bail!("arithmetic overflow, try --backend bigint")
+}
+
+fn part1(sensor_readings: &[Vec<i64>], fit: Option<usize>) -> Result<BigInt> {
+    Ok(extrapolate(sensor_readings, 1, fit, None)?.0)
+}
+
+fn part2(sensor_readings: &[Vec<i64>], fit: Option<usize>) -> Result<BigInt> {
+    Ok(extrapolate(sensor_readings, -1, fit, None)?.0)
 }
 
 fn main() -> Result<()> {
-    let filename = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "inputs/test1.txt".to_string());
+    let mut filename = "inputs/test1.txt".to_string();
+    let mut steps = Vec::new();
+    let mut fit = None;
+    let mut backend = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--steps" => steps.push(args.next().context("--steps needs a number")?.parse()?),
+            "--fit" => fit = Some(args.next().context("--fit needs a degree")?.parse()?),
+            "--backend" => backend = Some(args.next().context("--backend needs a type")?.parse()?),
+            opt if opt.starts_with("--") => bail!("unknown option {opt}"),
+            _ => filename = arg,
+        }
+    }
 
     let start = Instant::now();
     let sensor_readings = parse_sensor_readings(&filename)?;
@@ -80,14 +150,22 @@ fn main() -> Result<()> {
     println!("sensor readings parse time: {parse_duration:?}");
 
     let start = Instant::now();
-    let sum1 = part1(&sensor_readings);
+    let sum1 = part1(&sensor_readings, fit)?;
     let duration1 = start.elapsed();
     println!("part1: {sum1}, time: {duration1:?}");
 
     let start = Instant::now();
-    let sum2 = part2(&sensor_readings);
+    let sum2 = part2(&sensor_readings, fit)?;
     let duration2 = start.elapsed();
     println!("part2: {sum2}, time: {duration2:?}");
+
+    // e.g. --steps 10 --steps -10
+    for k in steps {
+        let start = Instant::now();
+        let (sum, used) = extrapolate(&sensor_readings, k, fit, backend)?;
+        let duration = start.elapsed();
+        println!("steps {k}: {sum} ({used:?}), time: {duration:?}");
+    }
     Ok(())
 }
 
@@ -98,16 +176,69 @@ mod tests {
     #[test]
     fn test_sample() {
         let filename = "src/inputs/test1.txt";
-        let sensor_readings = parse_sensor_readings(&filename).unwrap();
-        assert_eq!(114, part1(&sensor_readings));
-        assert_eq!(2, part2(&sensor_readings));
+        let sensor_readings = parse_sensor_readings(filename).unwrap();
+        assert_eq!(BigInt::from(114), part1(&sensor_readings, None).unwrap());
+        assert_eq!(BigInt::from(2), part2(&sensor_readings, None).unwrap());
     }
 
     #[test]
     fn test_solution() {
         let filename = "src/inputs/input.txt";
-        let sensor_readings = parse_sensor_readings(&filename).unwrap();
-        assert_eq!(1955513104, part1(&sensor_readings));
-        assert_eq!(1131, part2(&sensor_readings));
+        let sensor_readings = parse_sensor_readings(filename).unwrap();
+        assert_eq!(
+            BigInt::from(1955513104),
+            part1(&sensor_readings, None).unwrap()
+        );
+        assert_eq!(BigInt::from(1131), part2(&sensor_readings, None).unwrap());
+    }
+
+    #[test]
+    fn test_not_polynomial() {
+        let filename = "src/inputs/test2.txt";
+        let sensor_readings = parse_sensor_readings(filename).unwrap();
+        let err = part1(&sensor_readings, None).unwrap_err();
+        assert_eq!(
+            "line 2: 5 values are not enough to confirm a polynomial of degree 4",
+            format!("{err:#}")
+        );
+        // the best fit line through 1 2 4 8 16 is 3.6x - 1, giving 17 next
+        let fitted = part1(&sensor_readings, Some(1)).unwrap();
+        assert_eq!(BigInt::from(18 + 17 + 68), fitted);
+    }
+
+    #[test]
+    fn test_fit_out_of_range() {
+        let sensor_readings = vec![vec![1, 2, 4, 8, 16]];
+        let k = 10_000_000_000_000_000;
+        for backend in [None, Some(Backend::I64), Some(Backend::BigInt)] {
+            let err = extrapolate(&sensor_readings, k, Some(2), backend).unwrap_err();
+            assert!(err.to_string().contains("out of range"), "{err}");
+        }
+        for backend in [None, Some(Backend::BigInt)] {
+            let err = extrapolate(&sensor_readings, i64::MAX, Some(2), backend).unwrap_err();
+            assert!(err.to_string().contains("out of range"), "{err}");
+        }
+        // within range the fit still answers
+        assert!(extrapolate(&sensor_readings, 1000, Some(2), None).is_ok());
+    }
+
+    #[test]
+    fn test_backend_selection() {
+        let filename = "src/inputs/test1.txt";
+        let sensor_readings = parse_sensor_readings(filename).unwrap();
+        let (_, used) = extrapolate(&sensor_readings, 1, None, None).unwrap();
+        assert_eq!(Backend::I64, used);
+        // 10^10 steps on the cubic history overflows i64 but not i128
+        let k = 10_000_000_000;
+        let (wide, used) = extrapolate(&sensor_readings, k, None, None).unwrap();
+        assert_eq!(Backend::I128, used);
+        let (exact, _) = extrapolate(&sensor_readings, k, None, Some(Backend::BigInt)).unwrap();
+        assert_eq!(exact, wide);
+        assert!(extrapolate(&sensor_readings, k, None, Some(Backend::I64)).is_err());
+        // the step count itself is past i64 either way, only BigInt gets there
+        for k in [i64::MIN, i64::MAX] {
+            let (_, used) = extrapolate(&sensor_readings, k, None, None).unwrap();
+            assert_eq!(Backend::BigInt, used);
+        }
     }
 }
@@ -0,0 +1,73 @@
+// Least squares polynomial fit for histories that aren't exactly polynomial.
+// Solves the normal equations by Gaussian elimination, which is fine for the
+// low degrees this is meant for but gets ill-conditioned for high ones.
+
+use anyhow::{bail, Result};
+
+// Coefficients c[0] + c[1] x + c[2] x^2 ... of the best fit of the given degree
+// through (i, values[i])
+pub fn fit_polynomial(values: &[i64], degree: usize) -> Result<Vec<f64>> {
+    if degree >= values.len() {
+        bail!(
+            "{} values can't fit a polynomial of degree {degree}",
+            values.len()
+        );
+    }
+    let n = degree + 1;
+    // Augmented normal equations: sum_i x_i^(r + c) * a_c = sum_i x_i^r * y_i
+    let mut matrix = vec![vec![0.0; n + 1]; n];
+    for (x, &y) in values.iter().enumerate() {
+        let powers = (0..2 * n)
+            .map(|p| (x as f64).powi(p as i32))
+            .collect::<Vec<_>>();
+        for r in 0..n {
+            for c in 0..n {
+                matrix[r][c] += powers[r + c];
+            }
+            matrix[r][n] += powers[r] * y as f64;
+        }
+    }
+
+    // Gaussian elimination with partial pivoting
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))
+            .unwrap();
+        matrix.swap(col, pivot);
+        let pivot_row = matrix[col].clone();
+        for row in matrix.iter_mut().skip(col + 1) {
+            let factor = row[col] / pivot_row[col];
+            for (v, p) in row.iter_mut().zip(&pivot_row).skip(col) {
+                *v -= factor * p;
+            }
+        }
+    }
+    let mut coefficients = vec![0.0; n];
+    for row in (0..n).rev() {
+        let known = (row + 1..n)
+            .map(|c| matrix[row][c] * coefficients[c])
+            .sum::<f64>();
+        coefficients[row] = (matrix[row][n] - known) / matrix[row][row];
+    }
+    Ok(coefficients)
+}
+
+pub fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |sum, c| sum * x + c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_polynomial() {
+        // exact for n^2 + 1
+        let coefficients = fit_polynomial(&[1, 2, 5, 10, 17], 2).unwrap();
+        assert_eq!(26, evaluate(&coefficients, 5.0).round() as i64);
+        // best line through 0 1 0 1 is y = 0.2x + 0.2
+        let coefficients = fit_polynomial(&[0, 1, 0, 1], 1).unwrap();
+        assert!((evaluate(&coefficients, 4.0) - 1.0).abs() < 1e-9);
+        assert!(fit_polynomial(&[1, 2], 2).is_err());
+    }
+}